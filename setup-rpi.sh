@@ -112,6 +112,11 @@ if [ ! -f "finbert-onnx/tokenizer.json" ]; then
     exit 1
 fi
 
+if [ ! -f "finbert-onnx/config.json" ]; then
+    print_error "Model config file not found! Download may have failed."
+    exit 1
+fi
+
 print_status "✅ ONNX model files verified"
 
 # Set up environment variables
@@ -1,12 +1,18 @@
 use serde_json::Value;
 use reqwest::Client;
-use crate::types::OptionsQuery;
-use std::collections::HashSet;
+use crate::types::{
+    ChainContract, Greeks, NewsPage, NewsQuery, OccSymbol, OptionAnalysis, OptionChain, OptionChainResponse,
+    OptionContract, OptionContractInfo, OptionContractsPage, OptionRight, OptionsQuery, StockSnapshot,
+    SymbolOptionsAnalysis,
+};
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 use tokio::time::timeout;
 use once_cell::sync::Lazy;
-use dashmap::DashMap;
-use chrono::Datelike;
+use crate::cache::{canonical_key, RequestStats, TtlCache};
+use crate::clock::{Clock, DaysToExpiry};
+use crate::vol_surface::VolSurface;
+use crate::pricing::{black_scholes_merton, implied_volatility, model_implied_volatility, value_option, years_from_days, BsmInputs, PricingModel};
 
 // Global HTTP client for connection pooling
 static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
@@ -18,87 +24,180 @@ static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
         .expect("Failed to create HTTP client")
 });
 
-// Cache for news data (5 minute TTL)
-static NEWS_CACHE: Lazy<DashMap<String, (Value, std::time::Instant)>> = Lazy::new(|| {
-    DashMap::new()
-});
+// Cache for news data (NEWS_CACHE_TTL_SECS, default 5 minutes)
+pub static NEWS_CACHE: Lazy<TtlCache<NewsPage>> = Lazy::new(TtlCache::new);
 
-// Cache for options data (3 minute TTL)
-static OPTIONS_CACHE: Lazy<DashMap<String, (Value, std::time::Instant)>> = Lazy::new(|| {
-    DashMap::new()
-});
+// Cache for options data (OPTIONS_CACHE_TTL_SECS, default 3 minutes)
+pub static OPTIONS_CACHE: Lazy<TtlCache<OptionChain>> = Lazy::new(TtlCache::new);
+
+// Cache for underlying stock snapshots, per symbol (STOCK_CACHE_TTL_SECS, default 1 minute)
+pub static STOCK_CACHE: Lazy<TtlCache<StockSnapshot>> = Lazy::new(TtlCache::new);
+
+// Cache for contract reference data such as open interest (OPTIONS_CACHE_TTL_SECS)
+pub static CONTRACTS_CACHE: Lazy<TtlCache<OptionContractsPage>> = Lazy::new(TtlCache::new);
+
+// TTL for new cache entries, overridable through the environment
+fn cache_ttl(env_var: &str, default_secs: u64) -> Duration {
+    Duration::from_secs(
+        std::env::var(env_var)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(default_secs),
+    )
+}
+
+// Largest page the Alpaca news endpoint returns
+const NEWS_PAGE_SIZE: usize = 50;
 
-// Get News from Alpaca with timeout and retry logic
-pub async fn get_alpaca_news() -> Result<Value, String> {
+// Get News from Alpaca with timeout and retry logic, following pages until `query.limit` items
+pub async fn get_alpaca_news(query: &NewsQuery, stats: &RequestStats) -> Result<NewsPage, String> {
     let key = std::env::var("APCA_API_KEY_ID")
         .map_err(|_| "APCA_API_KEY_ID missing".to_string())?;
     let secret = std::env::var("APCA_API_SECRET_KEY")
         .map_err(|_| "APCA_API_SECRET_KEY missing".to_string())?;
     
-    // Check cache first
-    let cache_key = "alpaca_news";
-    if let Some(entry) = NEWS_CACHE.get(cache_key) {
-        let (cached_data, timestamp) = entry.value();
-        if timestamp.elapsed() < Duration::from_secs(300) { // 5 minutes
-            return Ok(cached_data.clone());
+    // helper to fetch a single page with retry logic
+    async fn do_request(headers: (&str, &str), query: &NewsQuery, page_limit: usize, page_token: Option<&str>, stats: &RequestStats) -> Result<NewsPage, String> {
+        let (key, secret) = headers;
+        
+        // Retry logic with exponential backoff
+        let mut attempt = 0;
+        let max_attempts = 3;
+        
+        while attempt < max_attempts {
+            let mut qp: Vec<(&str, String)> = vec![
+                ("sort", "desc".to_string()),
+                ("limit", page_limit.to_string()),
+                ("include_content", query.include_content.to_string()),
+                ("exclude_contentless", query.exclude_contentless.to_string()),
+            ];
+            if !query.symbols.is_empty() { qp.push(("symbols", query.symbols.join(","))); }
+            if let Some(v) = &query.start { qp.push(("start", v.clone())); }
+            if let Some(v) = &query.end { qp.push(("end", v.clone())); }
+            if let Some(v) = page_token { qp.push(("page_token", v.to_string())); }
+            
+            let req = HTTP_CLIENT.get("https://data.alpaca.markets/v1beta1/news")
+                .query(&qp)
+                .header("APCA-API-KEY-ID", key)
+                .header("APCA-API-SECRET-KEY", secret)
+                .header("accept", "application/json");
+            stats.record_api_call();
+            let resp = timeout(Duration::from_secs(60), req.send()).await
+                .map_err(|_| "Request timeout".to_string())?
+                .map_err(|e| format!("alpaca news req error: {e}"))?;
+            
+            if resp.status().is_success() {
+                return resp.json::<NewsPage>().await
+                    .map_err(|e| format!("alpaca news json error: {e}"));
+            }
+            
+            // If not successful, retry with exponential backoff
+            attempt += 1;
+            if attempt < max_attempts {
+                let delay = Duration::from_secs(2_u64.pow(attempt as u32));
+                tokio::time::sleep(delay).await;
+            }
         }
+        
+        Err("Failed to fetch news after all retry attempts".to_string())
     }
     
-    // Retry logic with exponential backoff
-    let mut attempt = 0;
-    let max_attempts = 3;
+    // Check cache first
+    let cache_key = canonical_key("news", query);
+    if let Some(cached_data) = NEWS_CACHE.get_for(&cache_key, stats) {
+        return Ok(cached_data);
+    }
     
-    while attempt < max_attempts {
-        let resp = timeout(
-            Duration::from_secs(60),
-            HTTP_CLIENT.get("https://data.alpaca.markets/v1beta1/news?sort=desc&limit=50")
-                .header("APCA-API-KEY-ID", key.clone())
-                .header("APCA-API-SECRET-KEY", secret.clone())
-                .header("accept", "application/json")
-                .send()
-        ).await
-            .map_err(|_| "Request timeout".to_string())?
-            .map_err(|e| format!("alpaca news req error: {e}"))?;
-        
-        if resp.status().is_success() {
-            let v = resp.json::<Value>().await
-                .map_err(|e| format!("alpaca news json error: {e}"))?;
-            
-            // Cache the result
-            NEWS_CACHE.insert(cache_key.to_string(), (v.clone(), std::time::Instant::now()));
-            return Ok(v);
+    let limit = query.limit.max(1);
+    let mut news = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let page_limit = (limit - news.len()).min(NEWS_PAGE_SIZE);
+        let page = do_request((&key, &secret), query, page_limit, page_token.as_deref(), stats).await?;
+        let page_was_empty = page.news.is_empty();
+        news.extend(page.news);
+        page_token = page.next_page_token.filter(|token| !token.is_empty());
+        if news.len() >= limit || page_token.is_none() || page_was_empty {
+            break;
+        }
+    }
+    news.truncate(limit);
+    
+    let result = NewsPage { news, next_page_token: page_token };
+    NEWS_CACHE.insert(cache_key, result.clone(), cache_ttl("NEWS_CACHE_TTL_SECS", 300));
+    Ok(result)
+}
+
+// Reduce an HTML article body to plain text: drop tags, script/style blocks and decode common entities
+pub fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start..];
+        // A '<' that cannot open a tag or comment ("P/E < 10") is plain text
+        if !after[1..].starts_with(|c: char| c.is_ascii_alphabetic() || matches!(c, '/' | '!' | '?')) {
+            text.push('<');
+            rest = &after[1..];
+            continue;
         }
+        let Some(end) = after.find('>') else {
+            // Unterminated tag, drop the remainder
+            rest = "";
+            break;
+        };
+        
+        let tag = after[1..end].trim_start_matches('/').to_ascii_lowercase();
+        let tag_name = tag.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or("");
+        rest = &after[end + 1..];
         
-        // If not successful, retry with exponential backoff
-        attempt += 1;
-        if attempt < max_attempts {
-            let delay = Duration::from_secs(2_u64.pow(attempt as u32));
-            tokio::time::sleep(delay).await;
+        // Skip everything inside script and style elements
+        if !after[1..].starts_with('/') && (tag_name == "script" || tag_name == "style") {
+            let closing = format!("</{tag_name}");
+            rest = match rest.to_ascii_lowercase().find(&closing) {
+                Some(pos) => rest[pos..].find('>').map_or("", |gt| &rest[pos + gt + 1..]),
+                None => "",
+            };
         }
+        
+        // Block-level tags separate words
+        text.push(' ');
     }
-    
-    Err("Failed to fetch news after all retry attempts".to_string())
+    text.push_str(rest);
+    
+    let decoded = text
+        .replace("&nbsp;", " ")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&rsquo;", "'")
+        .replace("&lsquo;", "'")
+        .replace("&rdquo;", "\"")
+        .replace("&ldquo;", "\"")
+        .replace("&mdash;", "-")
+        .replace("&ndash;", "-")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&");
+    
+    decoded.split_whitespace().collect::<Vec<_>>().join(" ")
 }
 
+// Default caps when following option chain pagination
+const DEFAULT_OPTIONS_MAX_PAGES: u32 = 10;
+const DEFAULT_OPTIONS_MAX_CONTRACTS: usize = 1000;
+
 // Get Options from Alpaca
-pub async fn fetch_alpaca_options(symbol: &str, q: &OptionsQuery) -> Result<Value, String> {
+pub async fn fetch_alpaca_options(symbol: &str, q: &OptionsQuery, stats: &RequestStats) -> Result<OptionChain, String> {
     let key = std::env::var("APCA_API_KEY_ID")
         .map_err(|_| "APCA_API_KEY_ID missing".to_string())?;
     let secret = std::env::var("APCA_API_SECRET_KEY")
         .map_err(|_| "APCA_API_SECRET_KEY missing".to_string())?;
-    // helper to perform a single request with an optional feed override and retry logic
-    async fn do_request(symbol: &str, headers: (&str, &str), q: &OptionsQuery, feed_override: Option<&str>) -> Result<Value, String> {
+    // helper to perform a single page request with an optional feed override and retry logic
+    async fn do_request(symbol: &str, headers: (&str, &str), q: &OptionsQuery, feed_override: Option<&str>, page_token: Option<&str>, stats: &RequestStats) -> Result<OptionChain, String> {
         let (key, secret) = headers;
         
-        // Check cache first
-        let cache_key = format!("options:{}:{}", symbol, feed_override.unwrap_or("indicative"));
-        if let Some(entry) = OPTIONS_CACHE.get(&cache_key) {
-            let (cached_data, timestamp) = entry.value();
-            if timestamp.elapsed() < Duration::from_secs(180) { // 3 minutes for options data
-                return Ok(cached_data.clone());
-            }
-        }
-        
         // Retry logic with exponential backoff
         let mut attempt = 0;
         let max_attempts = 3;
@@ -120,19 +219,16 @@ pub async fn fetch_alpaca_options(symbol: &str, q: &OptionsQuery) -> Result<Valu
             if let Some(v) = &q.expiration_date_gte { qp.push(("expiration_date_gte".into(), v.clone())); }
             if let Some(v) = &q.expiration_date_lte { qp.push(("expiration_date_lte".into(), v.clone())); }
             if let Some(v) = &q.root_symbol { qp.push(("root_symbol".into(), v.clone())); }
-            if let Some(v) = &q.page_token { qp.push(("page_token".into(), v.clone())); }
+            if let Some(v) = page_token { qp.push(("page_token".into(), v.to_string())); }
             req = req.query(&qp);
             
+            stats.record_api_call();
             let resp = timeout(Duration::from_secs(60), req.send()).await
                 .map_err(|_| "Request timeout".to_string())?
                 .map_err(|e| format!("alpaca req error: {e}"))?;
             
             if resp.status().is_success() {
-                let data = resp.json::<Value>().await.map_err(|e| format!("alpaca json error: {e}"))?;
-                
-                // Cache the result
-                OPTIONS_CACHE.insert(cache_key, (data.clone(), std::time::Instant::now()));
-                return Ok(data);
+                return resp.json::<OptionChain>().await.map_err(|e| format!("alpaca json error: {e}"));
             }
             
             // If not successful, retry with exponential backoff
@@ -149,7 +245,68 @@ pub async fn fetch_alpaca_options(symbol: &str, q: &OptionsQuery) -> Result<Valu
     let headers = (key.as_str(), secret.as_str());
     // Always include feed in the URL: use provided feed or default to indicative
     let feed = q.feed.as_deref().unwrap_or("indicative");
-    do_request(symbol, headers, q, Some(feed)).await
+    let fetch_all = q.fetch_all.unwrap_or(false);
+    
+    let ttl = cache_ttl("OPTIONS_CACHE_TTL_SECS", 180);
+    
+    // Check cache first; the key hashes the whole query (with the resolved feed)
+    // so different filters never share data
+    let cache_key = canonical_key(
+        &format!("options:{symbol}"),
+        &OptionsQuery { feed: Some(feed.to_string()), ..q.clone() },
+    );
+    if let Some(cached_data) = OPTIONS_CACHE.get_for(&cache_key, stats) {
+        return Ok(cached_data);
+    }
+    
+    if !fetch_all {
+        let mut data = do_request(symbol, headers, q, Some(feed), q.page_token.as_deref(), stats).await?;
+        data.pages_fetched = 1;
+        data.next_page_token = data.next_page_token.filter(|t| !t.is_empty());
+        data.truncated = data.next_page_token.is_some();
+        OPTIONS_CACHE.insert(cache_key, data.clone(), ttl);
+        return Ok(data);
+    }
+    
+    // Follow next_page_token until the chain is exhausted or a cap is reached
+    let max_pages = q.max_pages
+        .or_else(|| std::env::var("OPTIONS_MAX_PAGES").ok().and_then(|s| s.parse().ok()))
+        .unwrap_or(DEFAULT_OPTIONS_MAX_PAGES)
+        .max(1);
+    let max_contracts = q.max_contracts
+        .or_else(|| std::env::var("OPTIONS_MAX_CONTRACTS").ok().and_then(|s| s.parse().ok()))
+        .unwrap_or(DEFAULT_OPTIONS_MAX_CONTRACTS);
+    
+    let mut chain = OptionChain {
+        next_page_token: q.page_token.clone(),
+        ..Default::default()
+    };
+    
+    loop {
+        let page = do_request(symbol, headers, q, Some(feed), chain.next_page_token.as_deref(), stats).await?;
+        if !merge_chain_page(&mut chain, page, max_pages, max_contracts) {
+            break;
+        }
+    }
+    
+    if chain.truncated {
+        tracing::warn!(symbol, pages_fetched = chain.pages_fetched, contracts = chain.snapshots.len(), "Option chain truncated by pagination cap");
+    }
+    
+    OPTIONS_CACHE.insert(cache_key, chain.clone(), ttl);
+    Ok(chain)
+}
+
+// Add one fetched page to the chain and say whether to fetch another. Pages are kept whole and the
+// caps are only checked between them, so the last page can take the chain past max_contracts, but
+// next_page_token always resumes right after the last contract kept.
+fn merge_chain_page(chain: &mut OptionChain, page: OptionChain, max_pages: u32, max_contracts: usize) -> bool {
+    chain.pages_fetched += 1;
+    chain.snapshots.extend(page.snapshots);
+    chain.next_page_token = page.next_page_token.filter(|t| !t.is_empty());
+    chain.truncated = chain.next_page_token.is_some();
+    
+    chain.truncated && chain.pages_fetched < max_pages && chain.snapshots.len() < max_contracts
 }
 
 // Crypto filter - symbols that don't have traditional options
@@ -165,13 +322,225 @@ pub fn is_crypto_symbol(symbol: &str) -> bool {
     crypto_symbols.contains(symbol)
 }
 
-// Get Stocks from Alpaca
+// Symbols per multi-symbol stock snapshot request
+const STOCK_SNAPSHOT_BATCH_SIZE: usize = 100;
+
+// Get Stocks from Alpaca: latest trade/quote and daily bars for many symbols in batched requests
+pub async fn fetch_stock_snapshots(symbols: &[String], stats: &RequestStats) -> Result<HashMap<String, StockSnapshot>, String> {
+    let mut snapshots = HashMap::new();
+    let mut missing = Vec::new();
+    
+    // Serve what we can from the cache and only request the rest
+    for symbol in symbols {
+        match STOCK_CACHE.get_for(&format!("stock:{symbol}"), stats) {
+            Some(snapshot) => { snapshots.insert(symbol.clone(), snapshot); }
+            None => missing.push(symbol.clone()),
+        }
+    }
+    if missing.is_empty() {
+        return Ok(snapshots);
+    }
+    
+    let key = std::env::var("APCA_API_KEY_ID")
+        .map_err(|_| "APCA_API_KEY_ID missing".to_string())?;
+    let secret = std::env::var("APCA_API_SECRET_KEY")
+        .map_err(|_| "APCA_API_SECRET_KEY missing".to_string())?;
+    let feed = std::env::var("STOCK_FEED").unwrap_or_else(|_| "iex".to_string());
+    let ttl = cache_ttl("STOCK_CACHE_TTL_SECS", 60);
+    
+    for batch in missing.chunks(STOCK_SNAPSHOT_BATCH_SIZE) {
+        stats.record_api_call();
+        let resp = timeout(
+            Duration::from_secs(60),
+            HTTP_CLIENT.get("https://data.alpaca.markets/v2/stocks/snapshots")
+                .query(&[("symbols", batch.join(",")), ("feed", feed.clone())])
+                .header("APCA-API-KEY-ID", key.as_str())
+                .header("APCA-API-SECRET-KEY", secret.as_str())
+                .header("accept", "application/json")
+                .send()
+        ).await
+            .map_err(|_| "Request timeout".to_string())?
+            .map_err(|e| format!("alpaca stock snapshot req error: {e}"))?;
+        
+        if !resp.status().is_success() {
+            return Err(format!("alpaca stock snapshot error: HTTP {}", resp.status()));
+        }
+        
+        // Unknown symbols come back as null
+        let page = resp.json::<HashMap<String, Option<StockSnapshot>>>().await
+            .map_err(|e| format!("alpaca stock snapshot json error: {e}"))?;
+        
+        for (symbol, snapshot) in page {
+            if let Some(snapshot) = snapshot {
+                STOCK_CACHE.insert(format!("stock:{symbol}"), snapshot.clone(), ttl);
+                snapshots.insert(symbol, snapshot);
+            }
+        }
+    }
+    
+    Ok(snapshots)
+}
+
+// Underlying metrics reported per symbol and used for option pricing
+pub fn build_underlying_metrics(snapshot: &StockSnapshot) -> Value {
+    let spot_price = snapshot.spot_price();
+    let prev_close = snapshot.prev_daily_bar.as_ref().map(|b| b.close).filter(|c| *c > 0.0);
+    let change_pct = spot_price.zip(prev_close).map(|(spot, prev)| (spot - prev) / prev * 100.0);
+    
+    serde_json::json!({
+        "spot_price": spot_price,
+        "latest_trade": snapshot.latest_trade,
+        "latest_quote": snapshot.latest_quote,
+        "daily_bar": snapshot.daily_bar,
+        "prev_close": prev_close,
+        "change_pct": change_pct,
+    })
+}
+
+// Expiries and strikes the volatility surface is fitted on, and its own pagination caps
+const VOL_SURFACE_MAX_DAYS: i64 = 365;
+const VOL_SURFACE_STRIKE_BAND: (f64, f64) = (0.5, 2.0); // Multiples of spot
+const DEFAULT_VOL_SURFACE_MAX_PAGES: u32 = 3;
+const VOL_SURFACE_PAGE_SIZE: usize = 1000; // Largest page the snapshots endpoint returns
+
+// Page cap for the surface's chain fetch, kept apart from the chain endpoint's caps
+fn vol_surface_max_pages() -> u32 {
+    std::env::var("VOL_SURFACE_MAX_PAGES").ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|pages| *pages > 0)
+        .unwrap_or(DEFAULT_VOL_SURFACE_MAX_PAGES)
+}
+
+// Implied volatility surface for a symbol, fitted to the contracts expiring within a year and
+// struck within VOL_SURFACE_STRIKE_BAND of spot, up to VOL_SURFACE_MAX_PAGES pages of them
+pub async fn build_vol_surface(symbol: &str, spot_price: f64, clock: &dyn Clock, stats: &RequestStats) -> Result<VolSurface, String> {
+    let today = clock.today();
+    let max_pages = vol_surface_max_pages();
+    let options_query = OptionsQuery {
+        feed: Some("indicative".to_string()),
+        alpaca_limit: Some(VOL_SURFACE_PAGE_SIZE as i32),
+        strike_price_gte: Some((spot_price * VOL_SURFACE_STRIKE_BAND.0).floor()),
+        strike_price_lte: Some((spot_price * VOL_SURFACE_STRIKE_BAND.1).ceil()),
+        expiration_date_gte: Some(today.format("%Y-%m-%d").to_string()),
+        expiration_date_lte: Some((today + chrono::Duration::days(VOL_SURFACE_MAX_DAYS)).format("%Y-%m-%d").to_string()),
+        fetch_all: Some(true), // Both sides of every expiry
+        max_pages: Some(max_pages),
+        max_contracts: Some(max_pages as usize * VOL_SURFACE_PAGE_SIZE),
+        ..Default::default()
+    };
+    let chain = fetch_alpaca_options(symbol, &options_query, stats).await?;
+    let surface = VolSurface::build(
+        &chain,
+        spot_price,
+        risk_free_rate(),
+        dividend_yield(),
+        clock.today(),
+    );
+    if surface.is_empty() {
+        return Err(format!("No usable implied volatilities in the {symbol} option chain"));
+    }
+    Ok(surface)
+}
+
+// A symbol's option chain flattened into one row per contract: quote, volume, open interest,
+// IV (vendor, else solved from the mid) and greeks (vendor, else Black-Scholes-Merton)
+pub async fn fetch_normalized_chain(symbol: &str, query: &OptionsQuery, clock: &dyn Clock, stats: &RequestStats) -> Result<OptionChainResponse, String> {
+    let symbols = [symbol.to_string()];
+    let (chain, open_interest, stock) = tokio::join!(
+        fetch_alpaca_options(symbol, query, stats),
+        fetch_open_interest(symbol, query, stats),
+        fetch_stock_snapshots(&symbols, stats),
+    );
+    let chain = chain?;
+    let open_interest: HashMap<String, OptionContractInfo> = match open_interest {
+        Ok(page) => page.option_contracts.into_iter().map(|info| (info.symbol.clone(), info)).collect(),
+        Err(e) => {
+            tracing::warn!("Open interest unavailable for {}: {}", symbol, e);
+            HashMap::new()
+        }
+    };
+    let spot_price = stock.ok()
+        .and_then(|snapshots| snapshots.get(symbol).and_then(StockSnapshot::spot_price));
+    let rate = risk_free_rate();
+    let dividend_yield = dividend_yield();
+    
+    let contracts: Vec<ChainContract> = chain.snapshots.iter()
+        .filter_map(|(contract_key, snapshot)| {
+            let occ = contract_key.parse::<OccSymbol>().ok()?;
+            let days_to_expiry = DaysToExpiry::until(occ.expiration, clock);
+            let positive = |p: f64| (p > 0.0).then_some(p);
+            let bid = snapshot.latest_quote.as_ref().and_then(|q| positive(q.bid_price));
+            let ask = snapshot.latest_quote.as_ref().and_then(|q| positive(q.ask_price));
+            let mid = snapshot.latest_quote.as_ref().and_then(crate::types::Quote::mid);
+            let spread_pct = bid.zip(ask).zip(mid).map(|((bid, ask), mid)| (ask - bid) / mid * 100.0);
+            
+            let pricing_inputs = spot_price.map(|spot| BsmInputs {
+                spot,
+                strike: occ.strike(),
+                years: years_from_days(days_to_expiry.calendar_days()),
+                rate,
+                dividend_yield,
+                volatility: 0.0,
+                right: occ.right,
+            });
+            let (implied_volatility, iv_source) = match snapshot.implied_volatility.filter(|iv| *iv > 0.0) {
+                Some(iv) => (Some(iv), Some("vendor")),
+                None => {
+                    let solved = pricing_inputs.zip(mid)
+                        .and_then(|(inputs, mid)| implied_volatility(mid, &inputs).ok());
+                    (solved, solved.map(|_| "solved"))
+                }
+            };
+            let (greeks, greeks_source) = match &snapshot.greeks {
+                Some(greeks) => (Some(greeks.clone()), Some("vendor")),
+                None => {
+                    let model = pricing_inputs.zip(implied_volatility)
+                        .and_then(|(inputs, volatility)| black_scholes_merton(&BsmInputs { volatility, ..inputs }))
+                        .map(|m| Greeks { delta: m.delta, gamma: m.gamma, theta: m.theta, vega: m.vega, rho: m.rho });
+                    let source = model.as_ref().map(|_| "model");
+                    (model, source)
+                }
+            };
+            
+            Some(ChainContract {
+                symbol: contract_key.clone(),
+                option_type: occ.right,
+                strike_price: occ.strike(),
+                expiration_date: occ.expiration.format("%Y-%m-%d").to_string(),
+                days_to_expiry: days_to_expiry.calendar,
+                bid,
+                ask,
+                mid,
+                spread_pct,
+                last_price: snapshot.latest_trade.as_ref().and_then(|t| positive(t.price)),
+                volume: snapshot.daily_bar.as_ref().map(|b| b.volume.max(0.0) as u64).unwrap_or(0),
+                open_interest: open_interest.get(contract_key).and_then(|info| info.open_interest),
+                implied_volatility,
+                iv_source: iv_source.map(str::to_string),
+                greeks,
+                greeks_source: greeks_source.map(str::to_string),
+            })
+        })
+        .collect();
+    
+    Ok(OptionChainResponse {
+        symbol: symbol.to_string(),
+        underlying_price: spot_price,
+        pages_fetched: chain.pages_fetched,
+        total_contracts: contracts.len(),
+        returned_contracts: contracts.len(),
+        truncated: chain.truncated,
+        next_page_token: chain.next_page_token,
+        contracts,
+        timestamp: clock.now().to_rfc3339(),
+    })
+}
 
 // High Open Interest Result structure
 #[derive(Debug)]
 struct HighOpenInterestResult {
-    short_term: Option<Value>,
-    leap: Option<Value>,
+    short_term: Option<OptionContract>,
+    leap: Option<OptionContract>,
     error: Option<String>,
 }
 
@@ -179,185 +548,343 @@ struct HighOpenInterestResult {
 pub async fn analyze_ticker_options(
     symbol: &str,
     underlying_metrics: &Value,
-    option_type: Option<&str>,
-) -> Result<Value, String> {
+    vol_surface: Option<&VolSurface>,
+    option_type: &str,
+    clock: &dyn Clock,
+    stats: &RequestStats,
+) -> SymbolOptionsAnalysis {
     // Get high open interest contracts
-    let hoi_result = get_high_open_interest_contracts(symbol, option_type).await;
+    let mut hoi_result = get_high_open_interest_contracts(symbol, Some(option_type), clock, stats).await;
     
     let spot_price = underlying_metrics.get("spot_price").and_then(|v| v.as_f64()).unwrap_or(0.0);
+    
+    // Price every contract off the real underlying spot
+    for contract in hoi_result.short_term.iter_mut().chain(hoi_result.leap.iter_mut()) {
+        contract.underlying_price = (spot_price > 0.0).then_some(spot_price);
+    }
     let composite_score = underlying_metrics.get("metrics")
         .and_then(|m| m.get("composite_score"))
         .and_then(|s| s.as_f64())
         .unwrap_or(0.0);
     
-    let mut options_analysis = Vec::new();
-    
-    // Analyze both contract types and select the best one
-    let mut short_term_score = 0.0;
-    let mut leap_score = 0.0;
-    let mut short_term_contract = None;
-    let mut leap_contract = None;
-    
     // Calculate scores for both contract types
-    if let Some(contract) = hoi_result.short_term {
-        short_term_score = calculate_option_score(&contract, spot_price, composite_score);
-        short_term_contract = Some(contract);
-    }
-    
-    if let Some(contract) = hoi_result.leap {
-        leap_score = calculate_option_score(&contract, spot_price, composite_score);
-        leap_contract = Some(contract);
-    }
+    let short_term_score = hoi_result.short_term.as_ref()
+        .map(|contract| calculate_option_score(contract, spot_price, composite_score, clock))
+        .unwrap_or(0.0);
+    let leap_score = hoi_result.leap.as_ref()
+        .map(|contract| calculate_option_score(contract, spot_price, composite_score, clock))
+        .unwrap_or(0.0);
     
     // Select the best contract based on score
-    if short_term_score > leap_score {
+    let selected = if short_term_score > leap_score {
         // Short-term is better
-        if let Some(contract) = short_term_contract {
-            options_analysis.push(serde_json::json!({
-                "contract_type": "short_term",
-                "contract": contract,
-                "option_score": short_term_score,
-                "undervalued_indicators": calculate_undervalued_indicators(&contract, spot_price, composite_score)
-            }));
-        }
+        hoi_result.short_term.map(|contract| ("short_term", contract, short_term_score))
     } else if leap_score > 0.0 {
         // LEAP is better (or only option available)
-        if let Some(contract) = leap_contract {
-            options_analysis.push(serde_json::json!({
-                "contract_type": "leap",
-                "contract": contract,
-                "option_score": leap_score,
-                "undervalued_indicators": calculate_undervalued_indicators(&contract, spot_price, composite_score)
-            }));
-        }
+        hoi_result.leap.map(|contract| ("leap", contract, leap_score))
     } else if short_term_score > 0.0 {
         // Fallback to short-term if LEAP score is 0
-        if let Some(contract) = short_term_contract {
-            options_analysis.push(serde_json::json!({
-                "contract_type": "short_term",
-                "contract": contract,
-                "option_score": short_term_score,
-                "undervalued_indicators": calculate_undervalued_indicators(&contract, spot_price, composite_score)
-            }));
+        hoi_result.short_term.map(|contract| ("short_term", contract, short_term_score))
+    } else {
+        None
+    };
+    
+    let options_analysis = selected.into_iter()
+        .map(|(contract_type, contract, option_score)| OptionAnalysis {
+            contract_type: contract_type.to_string(),
+            option_type: option_type.to_string(),
+            undervalued_indicators: calculate_undervalued_indicators(&contract, spot_price, composite_score, vol_surface),
+            contract,
+            option_score,
+        })
+        .collect();
+    
+    SymbolOptionsAnalysis {
+        symbol: symbol.to_string(),
+        underlying_metrics: underlying_metrics.clone(),
+        options_analysis,
+        error: hoi_result.error,
+    }
+}
+
+// Expiry buckets used when picking contracts (days to expiry)
+const DEFAULT_SHORT_TERM_MAX_DAYS: i64 = 45;
+const DEFAULT_LEAP_MIN_DAYS: i64 = 270;
+
+// Minimum days to expiry for a contract to count as a LEAP
+fn leap_min_days() -> i64 {
+    std::env::var("LEAP_MIN_DAYS").ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_LEAP_MIN_DAYS)
+}
+
+// Largest page the Alpaca contracts endpoint returns
+const CONTRACTS_PAGE_SIZE: u32 = 10_000;
+// Highest open interest contracts per bucket whose snapshots are requested, in case some are unquoted
+const OPEN_INTEREST_CANDIDATES: usize = 10;
+
+// Open interest and expiration per contract from the Alpaca contracts endpoint, restricted to the
+// query's type, strike and expiry filters; next_page_token is left set when the page cap cut it short
+async fn fetch_open_interest(symbol: &str, query: &OptionsQuery, stats: &RequestStats) -> Result<OptionContractsPage, String> {
+    let key = std::env::var("APCA_API_KEY_ID")
+        .map_err(|_| "APCA_API_KEY_ID missing".to_string())?;
+    let secret = std::env::var("APCA_API_SECRET_KEY")
+        .map_err(|_| "APCA_API_SECRET_KEY missing".to_string())?;
+    let base_url = std::env::var("APCA_BASE_URL")
+        .unwrap_or_else(|_| "https://paper-api.alpaca.markets".to_string());
+    
+    // Only the filters the contracts endpoint understands take part in the key
+    let filters = OptionsQuery {
+        r#type: query.r#type.clone(),
+        strike_price_gte: query.strike_price_gte,
+        strike_price_lte: query.strike_price_lte,
+        expiration_date: query.expiration_date.clone(),
+        expiration_date_gte: query.expiration_date_gte.clone(),
+        expiration_date_lte: query.expiration_date_lte.clone(),
+        root_symbol: query.root_symbol.clone(),
+        ..Default::default()
+    };
+    let cache_key = canonical_key(&format!("contracts:{symbol}"), &filters);
+    if let Some(cached_data) = CONTRACTS_CACHE.get_for(&cache_key, stats) {
+        return Ok(cached_data);
+    }
+    
+    let max_pages = std::env::var("OPTIONS_MAX_PAGES").ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_OPTIONS_MAX_PAGES)
+        .max(1);
+    
+    let mut contracts = OptionContractsPage::default();
+    
+    for _ in 0..max_pages {
+        let mut qp: Vec<(&str, String)> = vec![
+            ("underlying_symbols", symbol.to_string()),
+            ("status", "active".to_string()),
+            ("limit", CONTRACTS_PAGE_SIZE.to_string()),
+        ];
+        if let Some(v) = &filters.r#type { qp.push(("type", v.clone())); }
+        if let Some(v) = filters.strike_price_gte { qp.push(("strike_price_gte", v.to_string())); }
+        if let Some(v) = filters.strike_price_lte { qp.push(("strike_price_lte", v.to_string())); }
+        if let Some(v) = &filters.expiration_date { qp.push(("expiration_date", v.clone())); }
+        if let Some(v) = &filters.expiration_date_gte { qp.push(("expiration_date_gte", v.clone())); }
+        if let Some(v) = &filters.expiration_date_lte { qp.push(("expiration_date_lte", v.clone())); }
+        if let Some(v) = &filters.root_symbol { qp.push(("root_symbol", v.clone())); }
+        if let Some(t) = &contracts.next_page_token { qp.push(("page_token", t.clone())); }
+        
+        stats.record_api_call();
+        let resp = timeout(
+            Duration::from_secs(60),
+            HTTP_CLIENT.get(format!("{base_url}/v2/options/contracts"))
+                .query(&qp)
+                .header("APCA-API-KEY-ID", key.as_str())
+                .header("APCA-API-SECRET-KEY", secret.as_str())
+                .header("accept", "application/json")
+                .send()
+        ).await
+            .map_err(|_| "Request timeout".to_string())?
+            .map_err(|e| format!("alpaca contracts req error: {e}"))?;
+        
+        if !resp.status().is_success() {
+            return Err(format!("alpaca contracts error: HTTP {}", resp.status()));
+        }
+        
+        let page = resp.json::<OptionContractsPage>().await
+            .map_err(|e| format!("alpaca contracts json error: {e}"))?;
+        
+        contracts.option_contracts.extend(page.option_contracts);
+        contracts.next_page_token = page.next_page_token.filter(|t| !t.is_empty());
+        if contracts.next_page_token.is_none() {
+            break;
         }
     }
     
-    Ok(serde_json::json!({
-        "symbol": symbol,
-        "underlying_metrics": underlying_metrics,
-        "options_analysis": options_analysis,
-        "error": hoi_result.error
-    }))
+    if contracts.next_page_token.is_some() {
+        tracing::warn!(symbol, contracts = contracts.option_contracts.len(), "Option contracts truncated by pagination cap");
+    }
+    
+    CONTRACTS_CACHE.insert(cache_key, contracts.clone(), cache_ttl("OPTIONS_CACHE_TTL_SECS", 180));
+    Ok(contracts)
 }
 
-// Debug function to log contract data structure
-fn debug_contract_data(contract: &Value, symbol: &str) {
-    eprintln!("DEBUG: Contract data for {}: {}", symbol, serde_json::to_string_pretty(contract).unwrap_or_else(|_| "Failed to serialize".to_string()));
+// Snapshots for specific option contracts from the multi-symbol snapshots endpoint
+async fn fetch_option_snapshots(contract_symbols: &[String], stats: &RequestStats) -> Result<OptionChain, String> {
+    let key = std::env::var("APCA_API_KEY_ID")
+        .map_err(|_| "APCA_API_KEY_ID missing".to_string())?;
+    let secret = std::env::var("APCA_API_SECRET_KEY")
+        .map_err(|_| "APCA_API_SECRET_KEY missing".to_string())?;
     
-    if let Some(contract_key) = contract.get("contract_key").and_then(|k| k.as_str()) {
-        eprintln!("DEBUG: Contract key: {}", contract_key);
-        eprintln!("DEBUG: Parsed strike price: {}", parse_strike_price_from_contract_key(contract_key));
-        eprintln!("DEBUG: Parsed expiration date: {}", parse_expiration_date_from_contract_key(contract_key));
-    } else {
-        eprintln!("DEBUG: No contract_key found in contract data");
+    let cache_key = canonical_key("options:snapshots", &contract_symbols);
+    if let Some(cached_data) = OPTIONS_CACHE.get_for(&cache_key, stats) {
+        return Ok(cached_data);
     }
+    
+    stats.record_api_call();
+    let resp = timeout(
+        Duration::from_secs(60),
+        HTTP_CLIENT.get("https://data.alpaca.markets/v1beta1/options/snapshots")
+            .query(&[("symbols", contract_symbols.join(",")), ("feed", "indicative".to_string())])
+            .header("APCA-API-KEY-ID", key.as_str())
+            .header("APCA-API-SECRET-KEY", secret.as_str())
+            .header("accept", "application/json")
+            .send()
+    ).await
+        .map_err(|_| "Request timeout".to_string())?
+        .map_err(|e| format!("alpaca option snapshot req error: {e}"))?;
+    
+    if !resp.status().is_success() {
+        return Err(format!("alpaca option snapshot error: HTTP {}", resp.status()));
+    }
+    
+    let chain = resp.json::<OptionChain>().await
+        .map_err(|e| format!("alpaca option snapshot json error: {e}"))?;
+    OPTIONS_CACHE.insert(cache_key, chain.clone(), cache_ttl("OPTIONS_CACHE_TTL_SECS", 180));
+    Ok(chain)
 }
 
-// Get high open interest contracts
-async fn get_high_open_interest_contracts(symbol: &str, option_type: Option<&str>) -> HighOpenInterestResult {
-    let mut result = HighOpenInterestResult {
-        short_term: None,
-        leap: None,
-        error: None,
-    };
+// Highest open interest contract matching the query that has a snapshot, and whether the
+// contract list it was picked from was truncated
+async fn highest_open_interest_contract(symbol: &str, query: &OptionsQuery, clock: &dyn Clock, stats: &RequestStats) -> Result<(Option<OptionContract>, bool), String> {
+    let contracts = fetch_open_interest(symbol, query, stats).await?;
+    let truncated = contracts.next_page_token.is_some();
+    
+    let mut candidates: Vec<(u64, chrono::NaiveDate, String)> = contracts.option_contracts.into_iter()
+        .filter_map(|info| {
+            let oi = info.open_interest.filter(|oi| *oi > 0)?;
+            let expiration = info.expiration_date.as_deref()
+                .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())?;
+            Some((oi, expiration, info.symbol))
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.2.cmp(&b.2)));
+    candidates.truncate(OPEN_INTEREST_CANDIDATES);
+    if candidates.is_empty() {
+        return Ok((None, truncated));
+    }
     
-    // Fetch options data
-    let options_query = crate::types::OptionsQuery {
-        feed: Some("indicative".to_string()),
+    let contract_symbols: Vec<String> = candidates.iter().map(|(_, _, symbol)| symbol.clone()).collect();
+    let mut snapshots = fetch_option_snapshots(&contract_symbols, stats).await?.snapshots;
+    
+    let best = candidates.into_iter().find_map(|(oi, expiration, contract_key)| {
+        let snapshot = snapshots.remove(&contract_key)?;
+        let days_to_expiry = DaysToExpiry::until(expiration, clock);
+        Some(OptionContract {
+            contract_key,
+            snapshot,
+            open_interest: Some(oi),
+            expiration_date: Some(expiration.format("%Y-%m-%d").to_string()),
+            days_to_expiry: Some(days_to_expiry.calendar),
+            trading_days_to_expiry: Some(days_to_expiry.trading),
+            ..Default::default()
+        })
+    });
+    Ok((best, truncated))
+}
+
+// Get the highest open interest contract in the short-term and LEAP expiry buckets; each bucket
+// is looked up with its own expiry filter so a long chain never crowds out the LEAPs
+async fn get_high_open_interest_contracts(symbol: &str, option_type: Option<&str>, clock: &dyn Clock, stats: &RequestStats) -> HighOpenInterestResult {
+    let short_term_max_days = std::env::var("SHORT_TERM_MAX_DAYS").ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_SHORT_TERM_MAX_DAYS);
+    let leap_min_days = leap_min_days();
+    
+    // Short term: 0 to short_term_max_days - 1 days out; LEAP: more than leap_min_days out
+    let today = clock.today();
+    let date_in = |days: i64| (today + chrono::Duration::days(days)).format("%Y-%m-%d").to_string();
+    let short_term_query = OptionsQuery {
         r#type: option_type.map(|t| t.to_string()),
-        alpaca_limit: Some(50), // Get more contracts to find high OI
+        expiration_date_gte: Some(date_in(0)),
+        expiration_date_lte: Some(date_in(short_term_max_days - 1)),
         ..Default::default()
     };
+    let leap_query = OptionsQuery {
+        r#type: option_type.map(|t| t.to_string()),
+        expiration_date_gte: Some(date_in(leap_min_days + 1)),
+        ..Default::default()
+    };
+    
+    let (short_term, leap) = tokio::join!(
+        highest_open_interest_contract(symbol, &short_term_query, clock, stats),
+        highest_open_interest_contract(symbol, &leap_query, clock, stats),
+    );
     
-    match fetch_alpaca_options(symbol, &options_query).await {
-        Ok(options_data) => {
-            if let Some(snapshots) = options_data.get("snapshots") {
-                if let Some(snapshots_obj) = snapshots.as_object() {
-                    // Find contracts with highest open interest
-                    let mut contracts: Vec<(&String, &Value)> = snapshots_obj.iter().collect();
-                    
-                    // Sort by open interest (if available) or use volume as proxy
-                    contracts.sort_by(|a, b| {
-                        let a_oi = a.1.get("latestQuote").and_then(|q| q.get("as")).and_then(|v| v.as_u64()).unwrap_or(0);
-                        let b_oi = b.1.get("latestQuote").and_then(|q| q.get("as")).and_then(|v| v.as_u64()).unwrap_or(0);
-                        b_oi.cmp(&a_oi) // Sort descending
-                    });
-                    
-                    // Take top contracts and add contract key information
-                    if !contracts.is_empty() {
-                        let mut contract_data = contracts[0].1.clone();
-                        // Add contract key information to the contract data
-                        contract_data["contract_key"] = serde_json::Value::String(contracts[0].0.clone());
-                        // Debug the contract data
-                        debug_contract_data(&contract_data, symbol);
-                        result.short_term = Some(contract_data);
-                    }
-                    if contracts.len() > 1 {
-                        let mut contract_data = contracts[1].1.clone();
-                        // Add contract key information to the contract data
-                        contract_data["contract_key"] = serde_json::Value::String(contracts[1].0.clone());
-                        // Debug the contract data
-                        debug_contract_data(&contract_data, symbol);
-                        result.leap = Some(contract_data);
-                    }
+    let mut result = HighOpenInterestResult {
+        short_term: None,
+        leap: None,
+        error: None,
+    };
+    let mut errors = Vec::new();
+    for (bucket, outcome, slot) in [("short-term", short_term, &mut result.short_term), ("LEAP", leap, &mut result.leap)] {
+        match outcome {
+            Ok((contract, truncated)) => {
+                if truncated {
+                    errors.push(format!("{bucket} contract list truncated by the page cap; the highest open interest contract may be missing"));
                 }
+                *slot = contract;
             }
-        }
-        Err(e) => {
-            result.error = Some(e);
+            Err(e) => errors.push(format!("{bucket}: {e}")),
         }
     }
-    
+    if !errors.is_empty() {
+        result.error = Some(errors.join("; "));
+    }
     result
 }
 
 // Calculate option score based on various factors
-fn calculate_option_score(contract: &Value, _spot_price: f64, composite_score: f64) -> f64 {
+fn calculate_option_score(contract: &OptionContract, spot_price: f64, composite_score: f64, clock: &dyn Clock) -> f64 {
     let mut score = 0.0;
     
     // Base score from composite sentiment
     score += composite_score * 0.3;
     
-    // Volume/Open Interest factor
-    if let Some(volume) = contract.get("latestQuote").and_then(|q| q.get("as")).and_then(|v| v.as_u64()) {
-        score += (volume as f64 / 1000.0).min(10.0); // Cap at 10 points
-    }
+    // Volume factor
+    score += (contract.volume() as f64 / 1000.0).min(10.0); // Cap at 10 points
     
     // Price factor (lower price = higher score for affordability)
-    if let Some(price) = contract.get("latestQuote").and_then(|q| q.get("ap")).and_then(|p| p.as_f64()) {
-        if price > 0.0 {
-            score += (1.0 / price).min(5.0); // Cap at 5 points
-        }
+    let price = contract.ask_price();
+    if price > 0.0 {
+        score += (1.0 / price).min(5.0); // Cap at 5 points
     }
     
     // Time to expiry factor (prefer contracts with reasonable time decay)
-    if let Some(expiry_str) = contract.get("contract_key").and_then(|k| k.as_str()) {
-        if let Some(days_to_expiry) = parse_days_to_expiry(expiry_str) {
-            if days_to_expiry < 30 {
-                // Very short-term options get penalty (high theta decay)
-                score -= 2.0;
-            } else if days_to_expiry > 365 {
-                // Very long-term options get slight penalty (less leverage)
-                score -= 1.0;
-            } else {
-                // Sweet spot: 30-365 days get bonus
-                score += 1.0;
-            }
+    if let Some(occ) = contract.occ() {
+        let days_to_expiry = DaysToExpiry::until(occ.expiration, clock).calendar;
+        if days_to_expiry < 30 {
+            // Very short-term options get penalty (high theta decay)
+            score -= 2.0;
+        } else if days_to_expiry > 365 {
+            // Very long-term options get slight penalty (less leverage)
+            score -= 1.0;
+        } else {
+            // Sweet spot: 30-365 days get bonus
+            score += 1.0;
+        }
+    }
+    
+    // Moneyness factor (prefer strikes near the underlying spot)
+    if let Some(occ) = contract.occ()
+        && spot_price > 0.0 {
+        let distance = (spot_price / occ.strike() - 1.0).abs();
+        if distance < 0.1 {
+            score += 1.0; // Near the money
+        } else if distance > 0.3 {
+            score -= 1.0; // Deep in or out of the money
+        }
+    }
+    
+    // Value factor: ask against the model fair value at the vendor IV
+    if let Some(fair_value) = contract_fair_value(contract, spot_price, clock)
+        && contract.ask_price() > 0.0 {
+        if contract.ask_price() <= fair_value {
+            score += 1.0; // At or below fair value
+        } else if contract.ask_price() > fair_value * 1.25 {
+            score -= 1.0; // Rich against the model
         }
     }
     
     // Liquidity factor (prefer higher open interest)
-    if let Some(oi) = contract.get("open_interest").and_then(|oi| oi.as_u64()) {
+    if let Some(oi) = contract.open_interest {
         if oi > 1000 {
             score += 2.0; // High liquidity bonus
         } else if oi > 100 {
@@ -370,61 +897,79 @@ fn calculate_option_score(contract: &Value, _spot_price: f64, composite_score: f
     score
 }
 
-// Helper function to parse days to expiry from contract key
-fn parse_days_to_expiry(contract_key: &str) -> Option<u32> {
-    // Contract key format: "AAPL240920C00150000" (AAPL + YYMMDD + C/P + Strike)
-    if contract_key.len() >= 10 {
-        let date_part = &contract_key[4..10]; // Extract YYMMDD
-        if let Ok(date_str) = date_part.parse::<u32>() {
-            let year = 2000 + (date_str / 10000);
-            let month = (date_str % 10000) / 100;
-            let day = date_str % 100;
-            
-            // Simple calculation: estimate days to expiry
-            // This is a rough approximation - in production you'd want proper date handling
-            let current_year = 2025; // Assuming current year
-            let current_month = 9;   // Assuming current month (September)
-            let current_day = 16;    // Assuming current day
-            
-            if year == current_year {
-                let days_in_month = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
-                let mut days_to_expiry = 0;
-                
-                // Add days from current month to expiry month
-                for m in current_month..month {
-                    days_to_expiry += days_in_month[m as usize - 1];
-                }
-                
-                // Add remaining days
-                days_to_expiry += day as i32 - current_day as i32;
-                
-                if days_to_expiry > 0 {
-                    return Some(days_to_expiry as u32);
-                }
-            }
-        }
+// Model value of a contract at its vendor IV, using the configured pricing model so puts and
+// dividend-paying calls carry their early-exercise value
+fn contract_fair_value(contract: &OptionContract, spot_price: f64, clock: &dyn Clock) -> Option<f64> {
+    let occ = contract.occ()?;
+    let vendor_iv = contract.snapshot.implied_volatility.filter(|iv| *iv > 0.0)?;
+    let inputs = BsmInputs {
+        spot: spot_price,
+        strike: occ.strike(),
+        years: years_from_days(DaysToExpiry::until(occ.expiration, clock).calendar_days()),
+        rate: risk_free_rate(),
+        dividend_yield: dividend_yield(),
+        volatility: 0.0,
+        right: occ.right,
+    };
+    let volatility = vendor_iv_for_model(vendor_iv, &inputs)?;
+    value_option(&BsmInputs { volatility, ..inputs }, pricing_model(), binomial_steps()).map(|v| v.value.price)
+}
+
+// Alpaca implies its IV with the closed form, so the early-exercise premium is already in it. When
+// the contract is valued on a tree, re-imply it there from its European price so the tree does not
+// add the premium a second time; None when that price is out of the tree's reach.
+fn vendor_iv_for_model(vendor_iv: f64, inputs: &BsmInputs) -> Option<f64> {
+    if pricing_model().for_contract(inputs) == PricingModel::BlackScholesMerton {
+        return Some(vendor_iv);
     }
-    None
+    let european = black_scholes_merton(&BsmInputs { volatility: vendor_iv, ..*inputs })?;
+    model_implied_volatility(european.price, inputs, pricing_model(), binomial_steps()).ok()
 }
 
+// Minimum IV discount to the fitted surface (absolute, 0.02 = 2 vol points) worth flagging
+const SURFACE_DISCOUNT_THRESHOLD: f64 = 0.02;
+
 // Calculate undervalued indicators
-fn calculate_undervalued_indicators(contract: &Value, _spot_price: f64, composite_score: f64) -> Vec<String> {
+fn calculate_undervalued_indicators(
+    contract: &OptionContract,
+    spot_price: f64,
+    composite_score: f64,
+    vol_surface: Option<&VolSurface>,
+) -> Vec<String> {
     let mut indicators = Vec::new();
     
     // High volume indicator
-    if let Some(volume) = contract.get("latestQuote").and_then(|q| q.get("as")).and_then(|v| v.as_u64()) {
-        if volume > 1000 {
-            indicators.push("High volume".to_string());
-        }
+    if contract.volume() > 1000 {
+        indicators.push("High volume".to_string());
     }
     
     // Low price indicator
-    if let Some(price) = contract.get("latestQuote").and_then(|q| q.get("ap")).and_then(|p| p.as_f64()) {
-        if price < 1.0 {
-            indicators.push("Low cost entry".to_string());
+    if contract.snapshot.latest_quote.is_some() && contract.ask_price() < 1.0 {
+        indicators.push("Low cost entry".to_string());
+    }
+    
+    // Ask below intrinsic value against the current spot
+    if let Some(occ) = contract.occ()
+        && spot_price > 0.0
+        && contract.ask_price() > 0.0 {
+        let intrinsic = match occ.right {
+            crate::types::OptionRight::Call => spot_price - occ.strike(),
+            crate::types::OptionRight::Put => occ.strike() - spot_price,
+        };
+        if contract.ask_price() < intrinsic {
+            indicators.push("Ask below intrinsic value".to_string());
         }
     }
     
+    // Implied volatility cheap against the symbol's fitted surface
+    if let Some(surface) = vol_surface
+        && let Some(occ) = contract.occ()
+        && let Some(fitted) = surface.iv_at(occ.strike(), occ.expiration)
+        && let Some(iv) = surface.contract_iv(&contract.snapshot, &occ)
+        && fitted - iv > SURFACE_DISCOUNT_THRESHOLD {
+        indicators.push(format!("IV {:.1} pts below fitted surface", (fitted - iv) * 100.0));
+    }
+    
     // Strong sentiment indicator
     if composite_score > 0.7 {
         indicators.push("Strong sentiment".to_string());
@@ -433,55 +978,27 @@ fn calculate_undervalued_indicators(contract: &Value, _spot_price: f64, composit
     indicators
 }
 
-// Calculate financial metrics for an option contract using options-specific data
-pub fn calculate_option_financial_metrics(contract: &Value) -> Option<crate::types::MetricsResult> {
+// Calculate financial metrics for an option contract using options-specific data; every metric
+// is driven by the implied volatility (from resolve_implied_volatility) and the moneyness, so
+// none without the IV, the strike or the underlying price
+pub fn calculate_option_financial_metrics(contract: &OptionContract, implied_volatility: Option<f64>, clock: &dyn Clock) -> Option<crate::types::MetricsResult> {
     // Extract option-specific data
-    let entry_price = contract.get("latestQuote")
-        .and_then(|q| q.get("ap"))
-        .and_then(|p| p.as_f64())
-        .unwrap_or(0.0);
-    
-    let strike_price = contract.get("contract_key")
-        .and_then(|k| k.as_str())
-        .map(parse_strike_price_from_contract_key)
-        .unwrap_or(0.0);
-    
-    let volume = contract.get("latestQuote")
-        .and_then(|q| q.get("as"))
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0) as f64;
-    
-    let implied_volatility = contract.get("implied_volatility")
-        .and_then(|iv| iv.as_f64())
-        .unwrap_or(0.3);
+    let entry_price = contract.ask_price();
+    let volume = contract.volume() as f64;
     
     // Skip if we don't have essential data
-    if entry_price <= 0.0 {
+    let implied_volatility = implied_volatility?;
+    let strike_price = contract.occ()?.strike();
+    let spot_price = contract.underlying_price?;
+    if entry_price <= 0.0 || strike_price <= 0.0 {
         return None;
     }
     
-    // Use a default strike price if not available
-    let strike_price = if strike_price > 0.0 { strike_price } else { entry_price * 1.1 };
-    
     // Calculate options-specific metrics
-    let time_to_expiry = calculate_time_to_expiry(contract);
-    
-    // Get spot price for proper moneyness calculation
-    let spot_price = contract.get("underlying_price")
-        .and_then(|p| p.as_f64())
-        .or_else(|| contract.get("spot_price").and_then(|p| p.as_f64()))
-        .or_else(|| contract.get("last_price").and_then(|p| p.as_f64()))
-        .unwrap_or_else(|| {
-            // Estimate spot price from strike and entry price
-            if strike_price > 0.0 {
-                strike_price * 0.95 // Assume slightly out-of-the-money
-            } else {
-                entry_price * 100.0 // Rough estimate
-            }
-        });
+    let time_to_expiry = calculate_time_to_expiry(contract, clock).calendar_days();
     
     // Calculate proper moneyness (spot/strike, not entry/strike)
-    let moneyness = if strike_price > 0.0 { spot_price / strike_price } else { 1.0 };
+    let moneyness = spot_price / strike_price;
     
     // Estimate expected return based on moneyness, volatility, and time to expiry
     let base_return = if moneyness > 0.9 && moneyness < 1.1 {
@@ -510,7 +1027,7 @@ pub fn calculate_option_financial_metrics(contract: &Value) -> Option<crate::typ
     let volatility = implied_volatility * (1.0 + (volume / 10000.0).min(1.0));
     
     // Calculate Sharpe ratio (more realistic)
-    let risk_free_rate = get_dynamic_risk_free_rate();
+    let risk_free_rate = risk_free_rate();
     let daily_risk_free = risk_free_rate / 252.0;
     let sharpe = if volatility > 0.0 {
         let excess_return = expected_return - daily_risk_free;
@@ -579,7 +1096,7 @@ pub fn calculate_option_financial_metrics(contract: &Value) -> Option<crate::typ
         
         // Ensure reasonable bounds
         if adjusted_kelly > 0.0 {
-            adjusted_kelly.max(0.02).min(0.25) // 2-25% position size
+            adjusted_kelly.clamp(0.02, 0.25) // 2-25% position size
         } else {
             0.0
         }
@@ -605,176 +1122,29 @@ pub fn calculate_option_financial_metrics(contract: &Value) -> Option<crate::typ
     })
 }
 
-// Calculate time to expiry in days
-fn calculate_time_to_expiry(contract: &Value) -> f64 {
-    // Try to get expiration date from contract key first
-    if let Some(expiration_str) = contract.get("contract_key")
-        .and_then(|k| k.as_str())
-        .map(parse_expiration_date_from_contract_key)
-        .filter(|s| !s.is_empty())
-    {
-        if let Ok(expiration_date) = chrono::NaiveDate::parse_from_str(&expiration_str, "%Y-%m-%d") {
-            let today = chrono::Utc::now().date_naive();
-            let duration = expiration_date.signed_duration_since(today);
-            let days = duration.num_days() as f64;
-            return if days > 0.0 { days } else { 1.0 }; // Minimum 1 day
-        }
-    }
-    
-    // Fallback: try to get from expiration_date field directly
-    if let Some(expiration_str) = contract.get("expiration_date")
-        .and_then(|e| e.as_str())
-        .filter(|s| !s.is_empty())
-    {
-        if let Ok(expiration_date) = chrono::NaiveDate::parse_from_str(expiration_str, "%Y-%m-%d") {
-            let today = chrono::Utc::now().date_naive();
-            let duration = expiration_date.signed_duration_since(today);
-            let days = duration.num_days() as f64;
-            return if days > 0.0 { days } else { 1.0 }; // Minimum 1 day
-        }
+// Calendar and trading days until the contract expires
+fn calculate_time_to_expiry(contract: &OptionContract, clock: &dyn Clock) -> DaysToExpiry {
+    // Try to get expiration date from contract key first, then the expiration_date field
+    let expiration_date = contract.occ()
+        .map(|occ| occ.expiration)
+        .or_else(|| contract.expiration_date.as_deref()
+            .and_then(|e| chrono::NaiveDate::parse_from_str(e, "%Y-%m-%d").ok()));
+    
+    match expiration_date {
+        Some(expiration_date) => DaysToExpiry::until(expiration_date, clock),
+        // Default to 30 days (about 21 trading days) if we can't parse
+        None => DaysToExpiry { calendar: 30, trading: 21 },
     }
-    
-    30.0 // Default to 30 days if we can't parse
-}
-
-// Parse strike price from contract key (format: SYMBOLYYMMDDC/PSSTRIKEPRICE)
-fn parse_strike_price_from_contract_key(contract_key: &str) -> f64 {
-    // Contract key format: SYMBOLYYMMDDC/PSSTRIKEPRICE
-    // Example: AAPL240119C00150000 (AAPL, 2024-01-19, Call, $150.00)
-    
-    // Handle different possible formats
-    if contract_key.len() >= 15 {
-        // Try the standard format first (last 8 characters)
-        let strike_part = &contract_key[contract_key.len()-8..];
-        if let Ok(strike_int) = strike_part.parse::<u32>() {
-            // Convert from integer representation to decimal (divide by 1000)
-            return strike_int as f64 / 1000.0;
-        }
-        
-        // Try alternative format (last 7 characters)
-        if contract_key.len() >= 14 {
-            let strike_part = &contract_key[contract_key.len()-7..];
-            if let Ok(strike_int) = strike_part.parse::<u32>() {
-                return strike_int as f64 / 1000.0;
-            }
-        }
-        
-        // Try alternative format (last 6 characters)
-        if contract_key.len() >= 13 {
-            let strike_part = &contract_key[contract_key.len()-6..];
-            if let Ok(strike_int) = strike_part.parse::<u32>() {
-                return strike_int as f64 / 1000.0;
-            }
-        }
-    }
-    
-    // If all parsing attempts fail, try to extract any numeric part at the end
-    let mut numeric_end = String::new();
-    for c in contract_key.chars().rev() {
-        if c.is_ascii_digit() {
-            numeric_end.push(c);
-        } else {
-            break;
-        }
-    }
-    
-    if !numeric_end.is_empty() {
-        numeric_end = numeric_end.chars().rev().collect();
-        if let Ok(strike_int) = numeric_end.parse::<u32>() {
-            // Try different scaling factors
-            if strike_int > 1000000 {
-                return strike_int as f64 / 1000.0; // 6+ digits, likely in thousandths
-            } else if strike_int > 10000 {
-                return strike_int as f64 / 100.0;  // 5 digits, likely in hundredths
-            } else {
-                return strike_int as f64; // 4 or fewer digits, likely whole dollars
-            }
-        }
-    }
-    
-    0.0
-}
-
-// Parse expiration date from contract key (format: SYMBOLYYMMDDC/PSSTRIKEPRICE)
-fn parse_expiration_date_from_contract_key(contract_key: &str) -> String {
-    // Contract key format: SYMBOLYYMMDDC/PSSTRIKEPRICE
-    // Example: AAPL240119C00150000 (AAPL, 2024-01-19, Call, $150.00)
-    
-    // Try different positions for the date part
-    let possible_positions = vec![
-        (15, 9),  // Standard format: last 15 chars, skip last 9
-        (14, 8),  // Alternative format: last 14 chars, skip last 8
-        (13, 7),  // Alternative format: last 13 chars, skip last 7
-        (12, 6),  // Alternative format: last 12 chars, skip last 6
-    ];
-    
-    for (total_len, skip_end) in possible_positions {
-        if contract_key.len() >= total_len {
-            let start_pos = contract_key.len() - total_len;
-            let end_pos = contract_key.len() - skip_end;
-            
-            if end_pos > start_pos && end_pos <= contract_key.len() {
-                let date_part = &contract_key[start_pos..end_pos];
-                if date_part.len() == 6 {
-                    // Parse YYMMDD format
-                    if let (Ok(year), Ok(month), Ok(day)) = (
-                        date_part[0..2].parse::<u32>(),
-                        date_part[2..4].parse::<u32>(),
-                        date_part[4..6].parse::<u32>(),
-                    ) {
-                        // Validate date components
-                        if month >= 1 && month <= 12 && day >= 1 && day <= 31 {
-                            // Convert 2-digit year to 4-digit (assuming 20xx)
-                            let full_year = 2000 + year;
-                            return format!("{:04}-{:02}-{:02}", full_year, month, day);
-                        }
-                    }
-                }
-            }
-        }
-    }
-    
-    // If standard parsing fails, try to find any 6-digit sequence that looks like a date
-    for i in 0..=contract_key.len().saturating_sub(6) {
-        let date_part = &contract_key[i..i+6];
-        if date_part.chars().all(|c| c.is_ascii_digit()) {
-            if let (Ok(year), Ok(month), Ok(day)) = (
-                date_part[0..2].parse::<u32>(),
-                date_part[2..4].parse::<u32>(),
-                date_part[4..6].parse::<u32>(),
-            ) {
-                // Validate date components
-                if month >= 1 && month <= 12 && day >= 1 && day <= 31 {
-                    // Convert 2-digit year to 4-digit (assuming 20xx)
-                    let full_year = 2000 + year;
-                    return format!("{:04}-{:02}-{:02}", full_year, month, day);
-                }
-            }
-        }
-    }
-    
-    String::new()
 }
 
 // Fundamental risk assessment for a symbol
-pub fn assess_fundamental_risk(symbol: &str, contract: &serde_json::Value) -> (f64, Vec<String>) {
+pub fn assess_fundamental_risk(symbol: &str, contract: &OptionContract, implied_volatility: Option<f64>) -> (f64, Vec<String>) {
     let mut risk_factors = Vec::new();
     let mut risk_score = 0.0;
     
     // Extract basic price data
-    let entry_price = contract.get("latestQuote")
-        .and_then(|q| q.get("ap"))
-        .and_then(|p| p.as_f64())
-        .unwrap_or(0.0);
-    
-    let volume = contract.get("latestQuote")
-        .and_then(|q| q.get("as"))
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0);
-    
-    let open_interest = contract.get("open_interest")
-        .and_then(|oi| oi.as_u64())
-        .unwrap_or(0);
+    let entry_price = contract.ask_price();
+    let volume = contract.volume();
     
     // 1. Price-based risk filters
     if entry_price < 0.05 {
@@ -794,9 +1164,16 @@ pub fn assess_fundamental_risk(symbol: &str, contract: &serde_json::Value) -> (f
         risk_factors.push("Low volume (<500) - liquidity concerns".to_string());
     }
     
-    if open_interest < 50 {
-        risk_score += 0.2;
-        risk_factors.push("Very low open interest (<50) - limited liquidity".to_string());
+    match contract.open_interest {
+        Some(oi) if oi < 50 => {
+            risk_score += 0.2;
+            risk_factors.push("Very low open interest (<50) - limited liquidity".to_string());
+        }
+        None => {
+            risk_score += 0.2;
+            risk_factors.push("Open interest unknown - liquidity unverified".to_string());
+        }
+        Some(_) => {}
     }
     
     // 3. Sector-specific risk filters
@@ -804,17 +1181,21 @@ pub fn assess_fundamental_risk(symbol: &str, contract: &serde_json::Value) -> (f
     risk_score += sector_risk.0;
     risk_factors.extend(sector_risk.1);
     
-    // 4. Volatility risk filter
-    let implied_volatility = contract.get("implied_volatility")
-        .and_then(|iv| iv.as_f64())
-        .unwrap_or(0.3);
-    
-    if implied_volatility > 1.0 {
-        risk_score += 0.3;
-        risk_factors.push("Extreme volatility (>100%) - high risk".to_string());
-    } else if implied_volatility > 0.8 {
-        risk_score += 0.2;
-        risk_factors.push("Very high volatility (>80%) - elevated risk".to_string());
+    // 4. Volatility risk filter; an unknown IV is scored as the worst case
+    match implied_volatility {
+        None => {
+            risk_score += 0.3;
+            risk_factors.push("Implied volatility unavailable - volatility risk unknown".to_string());
+        }
+        Some(iv) if iv > 1.0 => {
+            risk_score += 0.3;
+            risk_factors.push("Extreme volatility (>100%) - high risk".to_string());
+        }
+        Some(iv) if iv > 0.8 => {
+            risk_score += 0.2;
+            risk_factors.push("Very high volatility (>80%) - elevated risk".to_string());
+        }
+        Some(_) => {}
     }
     
     // 5. Market cap estimation (rough)
@@ -898,165 +1279,115 @@ fn estimate_market_cap(symbol: &str, price: f64) -> f64 {
     price * estimated_shares
 }
 
+// Implied volatility of a selected contract under its pricing model, and where it came from
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedIv {
+    pub value: Option<f64>,
+    pub source: Option<&'static str>, // "vendor" or "solved"
+    pub solver_error: Option<String>, // Why solving from the quote mid failed, if it was attempted
+}
+
+// The vendor's implied volatility, else one solved from the quote mid against the real underlying
+// price; None when neither is available, never an estimate. Either way it is implied under the
+// model the contract is valued with, so valuing it at this IV does not double-count early exercise.
+pub fn resolve_implied_volatility(contract: &OptionContract, clock: &dyn Clock) -> ResolvedIv {
+    let vendor_iv = contract.snapshot.implied_volatility.filter(|iv| *iv > 0.0);
+    let inputs = contract.occ().zip(contract.underlying_price).map(|(occ, spot)| BsmInputs {
+        spot,
+        strike: occ.strike(),
+        years: years_from_days(calculate_time_to_expiry(contract, clock).calendar_days()),
+        rate: risk_free_rate(),
+        dividend_yield: dividend_yield(),
+        volatility: 0.0,
+        right: occ.right,
+    });
+    if let Some(iv) = vendor_iv {
+        // Without an underlying price the contract is not valued, so the vendor's figure stands
+        let value = inputs.map_or(Some(iv), |inputs| vendor_iv_for_model(iv, &inputs));
+        return ResolvedIv { value, source: value.map(|_| "vendor"), solver_error: None };
+    }
+    let (Some(inputs), Some(mid)) = (inputs, contract.mid_price()) else {
+        return ResolvedIv::default();
+    };
+    match model_implied_volatility(mid, &inputs, pricing_model(), binomial_steps()) {
+        Ok(iv) => ResolvedIv { value: Some(iv), source: Some("solved"), solver_error: None },
+        Err(e) => {
+            tracing::debug!("IV solve failed for {}: {}", contract.contract_key, e);
+            ResolvedIv { solver_error: Some(e.to_string()), ..Default::default() }
+        }
+    }
+}
+
 // Convert option analysis to trading signal with fundamental risk filtering
 pub fn convert_to_trading_signal(
     symbol: &str,
     option_analysis: &crate::types::OptionAnalysis,
-    sentiment_score: f64,
-    overall_sentiment: &str,
+    net_sentiment: f64,
+    clock: &dyn Clock,
 ) -> crate::types::TradingSignal {
+    let days_to_expiry = calculate_time_to_expiry(&option_analysis.contract, clock);
+    let time_to_expiry = days_to_expiry.calendar_days();
+    let is_call = option_analysis.option_type == "call";
     let contract = &option_analysis.contract;
+    let iv = resolve_implied_volatility(contract, clock);
     
     // Perform fundamental risk assessment
-    let (fundamental_risk_score, risk_factors) = assess_fundamental_risk(symbol, contract);
+    let (fundamental_risk_score, risk_factors) = assess_fundamental_risk(symbol, contract, iv.value);
     
     // Extract option data
-    let entry_price = contract.get("latestQuote")
-        .and_then(|q| q.get("ap"))
-        .and_then(|p| p.as_f64())
-        .unwrap_or(0.0);
+    let entry_price = contract.ask_price();
     
-    // Extract strike price from contract key with debugging
-    let strike_price = if let Some(contract_key) = contract.get("contract_key").and_then(|k| k.as_str()) {
-        let parsed_strike = parse_strike_price_from_contract_key(contract_key);
-        if parsed_strike > 0.0 {
-            parsed_strike
-        } else {
-            // Try to extract from other possible fields
-            contract.get("strike_price")
-                .and_then(|s| s.as_f64())
-                .or_else(|| contract.get("strike").and_then(|s| s.as_f64()))
-                .unwrap_or(0.0)
-        }
-    } else {
-        // Fallback: try to extract from other possible fields
-        contract.get("strike_price")
-            .and_then(|s| s.as_f64())
-            .or_else(|| contract.get("strike").and_then(|s| s.as_f64()))
-            .unwrap_or(0.0)
-    };
-    
-    // Extract expiration date from contract key with debugging
-    let expiration_date = if let Some(contract_key) = contract.get("contract_key").and_then(|k| k.as_str()) {
-        let parsed_date = parse_expiration_date_from_contract_key(contract_key);
-        if !parsed_date.is_empty() {
-            parsed_date
-        } else {
-            // Fallback to contract field if available
-            contract.get("expiration_date")
-                .and_then(|e| e.as_str())
-                .unwrap_or("")
-                .to_string()
-        }
-    } else {
-        // Fallback to contract field if available
-        contract.get("expiration_date")
-            .and_then(|e| e.as_str())
-            .unwrap_or("")
-            .to_string()
-    };
-    
-    let volume = contract.get("latestQuote")
-        .and_then(|q| q.get("as"))
-        .and_then(|v| v.as_u64())
-        .unwrap_or(0);
-    
-    // Extract open interest - try multiple possible field names
-    let open_interest = contract.get("open_interest")
-        .and_then(|oi| oi.as_u64())
-        .or_else(|| contract.get("oi").and_then(|oi| oi.as_u64()))
-        .or_else(|| contract.get("openInterest").and_then(|oi| oi.as_u64()))
-        .or_else(|| contract.get("outstanding_contracts").and_then(|oi| oi.as_u64()))
-        .or_else(|| {
-            // Fallback to volume as proxy for open interest when OI is not available
-            // This is common in market data APIs where OI is not provided
-            contract.get("latestQuote")
-                .and_then(|q| q.get("as"))
-                .and_then(|v| v.as_u64())
-        })
-        .unwrap_or(0);
-    
-    // Calculate Greeks dynamically based on option characteristics
-    let implied_volatility = contract.get("implied_volatility")
-        .and_then(|iv| iv.as_f64())
-        .or_else(|| contract.get("iv").and_then(|iv| iv.as_f64()))
-        .or_else(|| contract.get("impliedVolatility").and_then(|iv| iv.as_f64()))
-        .unwrap_or_else(|| {
-            // Estimate IV based on volume and time to expiry
-            let time_to_expiry = calculate_time_to_expiry(contract);
-            let volume_factor = (volume as f64 / 10000.0).min(1.0);
-            let base_iv = 0.2 + (time_to_expiry / 365.0) * 0.1; // 20-30% base IV
-            base_iv + volume_factor * 0.1 // Add up to 10% based on volume
-        });
-    
-    // Get underlying asset price (spot price) - this should be different from entry_price
-    let spot_price = contract.get("underlying_price")
-        .and_then(|p| p.as_f64())
-        .or_else(|| contract.get("spot_price").and_then(|p| p.as_f64()))
-        .or_else(|| contract.get("last_price").and_then(|p| p.as_f64()))
-        .or_else(|| {
-            // Estimate spot price from strike and moneyness
-            if strike_price > 0.0 {
-                // Assume at-the-money or slightly out-of-the-money
-                Some(strike_price * 0.95) // 5% below strike as estimate
-            } else {
-                Some(entry_price * 100.0) // Rough estimate if no other data
-            }
-        });
+    // Extract strike and expiration from the OCC contract key
+    let occ = contract.occ();
+    let strike_price = occ.as_ref()
+        .map(|occ| occ.strike())
+        .unwrap_or(0.0);
     
-    // Calculate Greeks using Black-Scholes approximations
-    let (delta, gamma, theta, vega) = if let Some(spot) = spot_price {
-        calculate_option_greeks(
-            spot, strike_price, implied_volatility, 
-            calculate_time_to_expiry(contract), overall_sentiment == "call"
+    let expiration_date = occ.as_ref()
+        .map(|occ| occ.expiration.format("%Y-%m-%d").to_string())
+        .or_else(|| contract.expiration_date.clone())
+        .unwrap_or_default();
+    
+    let volume = contract.volume();
+    let open_interest = contract.open_interest;
+    
+    // Underlying price from the stock snapshot; without it the contract is neither valued
+    // nor given an expected return
+    let spot_price = contract.underlying_price;
+    
+    // Model value and greeks at the resolved volatility, American where early exercise matters;
+    // without an implied volatility the contract is not valued at all
+    let valuation = spot_price.zip(iv.value).and_then(|(spot, volatility)| {
+        value_option(
+            &BsmInputs {
+                spot,
+                strike: strike_price,
+                years: years_from_days(time_to_expiry),
+                rate: risk_free_rate(),
+                dividend_yield: dividend_yield(),
+                volatility,
+                right: if is_call { OptionRight::Call } else { OptionRight::Put },
+            },
+            pricing_model(),
+            binomial_steps(),
         )
-    } else {
-        (0.0, 0.0, 0.0, 0.0) // Fallback if no spot price available
+    });
+    
+    // Use the vendor's greeks when present, otherwise the model's
+    let (greeks, greeks_source) = match (&contract.snapshot.greeks, valuation.map(|v| v.value)) {
+        (Some(greeks), _) => (Some(greeks.clone()), Some("vendor")),
+        (None, Some(model)) => (
+            Some(Greeks { delta: model.delta, gamma: model.gamma, theta: model.theta, vega: model.vega, rho: model.rho }),
+            Some("model"),
+        ),
+        (None, None) => (None, None),
     };
     
-    // Determine signal type based on sentiment score and overall sentiment
-    // Use more aggressive thresholds to create variety in signals
-    let signal_type = if sentiment_score > 0.9 {
-        // Very strong positive sentiment -> calls
-        match option_analysis.contract_type.as_str() {
-            "short_term" => "BUY_CALL",
-            "leap" => "BUY_CALL",
-            _ => "BUY_CALL",
-        }
-    } else if sentiment_score < 0.2 {
-        // Strong negative sentiment -> puts
-        match option_analysis.contract_type.as_str() {
-            "short_term" => "BUY_PUT",
-            "leap" => "BUY_PUT",
-            _ => "BUY_PUT",
-        }
-    } else if sentiment_score > 0.7 {
-        // Moderate positive sentiment -> calls
-        match option_analysis.contract_type.as_str() {
-            "short_term" => "BUY_CALL",
-            "leap" => "BUY_CALL",
-            _ => "BUY_CALL",
-        }
-    } else if sentiment_score < 0.4 {
-        // Moderate negative sentiment -> puts
-        match option_analysis.contract_type.as_str() {
-            "short_term" => "BUY_PUT",
-            "leap" => "BUY_PUT",
-            _ => "BUY_PUT",
-        }
-    } else {
-        // Neutral sentiment (0.4-0.7) - use overall market sentiment
-        match (overall_sentiment, option_analysis.contract_type.as_str()) {
-            ("call", "short_term") => "BUY_CALL",
-            ("call", "leap") => "BUY_CALL",
-            ("put", "short_term") => "BUY_PUT", 
-            ("put", "leap") => "BUY_PUT",
-            _ => "BUY_CALL",
-        }
-    };
+    // The side was chosen from this symbol's sentiment when the chain was fetched
+    let signal_type = if is_call { "BUY_CALL" } else { "BUY_PUT" };
     
     // Calculate risk metrics
-    let financial_metrics = if let Some(metrics) = calculate_option_financial_metrics(contract) {
+    let financial_metrics = if let Some(metrics) = calculate_option_financial_metrics(contract, iv.value, clock) {
         crate::types::FinancialMetrics {
             sharpe_ratio: metrics.sharpe,
             sortino_ratio: metrics.sortino,
@@ -1065,8 +1396,8 @@ pub fn convert_to_trading_signal(
             volatility: metrics.volatility,
             composite_score: metrics.composite_score,
             kelly_fraction: metrics.kelly_fraction,
-            var_95: calculate_dynamic_var_95(metrics.volatility, metrics.mean_return, calculate_time_to_expiry(contract)),
-            expected_shortfall: calculate_dynamic_expected_shortfall(metrics.volatility, metrics.mean_return, calculate_time_to_expiry(contract)),
+            var_95: calculate_dynamic_var_95(metrics.volatility, metrics.mean_return, days_to_expiry.trading_days()),
+            expected_shortfall: calculate_dynamic_expected_shortfall(metrics.volatility, metrics.mean_return, days_to_expiry.trading_days()),
         }
     } else {
         crate::types::FinancialMetrics {
@@ -1083,24 +1414,22 @@ pub fn convert_to_trading_signal(
     };
     
     // Calculate expected return dynamically based on option characteristics
-    let expected_return = if let Some(spot) = spot_price {
+    let expected_return = spot_price.zip(iv.value).map(|(spot, implied_volatility)| {
         calculate_expected_option_return(
             entry_price, strike_price, spot, implied_volatility, 
-            calculate_time_to_expiry(contract), overall_sentiment == "call",
+            time_to_expiry, is_call,
             volume, open_interest
         )
-    } else {
-        0.0 // Fallback if no spot price available
-    };
+    });
     let max_loss = entry_price; // For long options, max loss is premium paid
     
     // Determine time horizon based on actual expiration date
-    let time_horizon = calculate_time_horizon_from_expiration(&expiration_date);
+    let time_horizon = calculate_time_horizon_from_expiration(&expiration_date, clock);
     
     // Calculate combined risk score (technical + fundamental)
     let technical_risk_score = calculate_dynamic_risk_score(
-        implied_volatility, financial_metrics.max_drawdown, 
-        volume, open_interest, calculate_time_to_expiry(contract)
+        iv.value, financial_metrics.max_drawdown, 
+        volume, open_interest, time_to_expiry
     );
     
     // Combine technical and fundamental risk (weighted average)
@@ -1108,8 +1437,11 @@ pub fn convert_to_trading_signal(
     
     // Generate reasoning
     let mut reasoning = Vec::new();
-    reasoning.push(format!("Sentiment: {overall_sentiment} (confidence: {sentiment_score:.2})"));
+    reasoning.push(format!("Net sentiment: {net_sentiment:+.2} ({})", option_analysis.option_type));
     reasoning.extend(option_analysis.undervalued_indicators.clone());
+    if spot_price.is_none() {
+        reasoning.push("Underlying price unavailable - contract not valued".to_string());
+    }
     
     // Add fundamental risk warnings if present
     if !risk_factors.is_empty() {
@@ -1126,7 +1458,7 @@ pub fn convert_to_trading_signal(
     
     // Calculate confidence score dynamically based on multiple factors
     let base_confidence = calculate_dynamic_confidence(
-        sentiment_score, option_analysis.option_score, 
+        net_sentiment.abs(), option_analysis.option_score, 
         financial_metrics.composite_score, volume, open_interest
     );
     
@@ -1149,7 +1481,7 @@ pub fn convert_to_trading_signal(
         symbol: symbol.to_string(),
         signal_type: signal_type.to_string(),
         confidence,
-        sentiment_score,
+        sentiment_score: net_sentiment,
         risk_score,
         expected_return,
         max_loss,
@@ -1159,20 +1491,89 @@ pub fn convert_to_trading_signal(
         expiration_date,
         volume,
         open_interest,
-        implied_volatility,
-        delta,
-        gamma,
-        theta,
-        vega,
+        implied_volatility: iv.value,
+        iv_source: iv.source.map(str::to_string),
+        iv_solver_error: iv.solver_error,
+        greeks,
+        greeks_source: greeks_source.map(str::to_string),
+        model_price: valuation.map(|v| v.value.price),
+        pricing_model: valuation.map(|v| v.model.as_str().to_string()),
+        early_exercise_premium: valuation.map(|v| v.early_exercise_premium),
         financial_metrics,
         reasoning,
     }
 }
 
+// Aggregate news sentiment per symbol, weighting each article by model confidence
+// and an exponential decay on its age (weight halves every `half_life_hours`)
+pub fn aggregate_symbol_sentiment(
+    news: &[crate::types::SentimentAnalysis],
+    now: chrono::DateTime<chrono::Utc>,
+    half_life_hours: f64,
+) -> Vec<crate::types::SymbolSentiment> {
+    // symbol -> (weight, net score, publish time) per article
+    let mut by_symbol = std::collections::HashMap::<&str, Vec<_>>::new();
+    
+    for item in news {
+        let published = item.created_at.as_deref()
+            .and_then(|ts| chrono::DateTime::parse_from_rfc3339(ts).ok())
+            .map(|ts| ts.with_timezone(&chrono::Utc));
+        
+        // Articles without a timestamp are treated as fresh
+        let age_hours = published
+            .map(|ts| (now - ts).num_seconds().max(0) as f64 / 3600.0)
+            .unwrap_or(0.0);
+        let decay = if half_life_hours > 0.0 {
+            (-std::f64::consts::LN_2 * age_hours / half_life_hours).exp()
+        } else {
+            1.0
+        };
+        let weight = item.confidence * decay;
+        
+        for symbol in &item.symbols {
+            by_symbol.entry(symbol.as_str()).or_default().push((weight, item.net_score, published));
+        }
+    }
+    
+    let mut aggregated: Vec<crate::types::SymbolSentiment> = by_symbol.into_iter()
+        .map(|(symbol, articles)| {
+            let weight_sum: f64 = articles.iter().map(|(w, _, _)| w).sum();
+            let (score, dispersion) = if weight_sum > 0.0 {
+                let mean = articles.iter().map(|(w, net, _)| w * net).sum::<f64>() / weight_sum;
+                let variance = articles.iter()
+                    .map(|(w, net, _)| w * (net - mean).powi(2))
+                    .sum::<f64>() / weight_sum;
+                (mean, variance.sqrt())
+            } else {
+                (0.0, 0.0)
+            };
+            
+            let latest_timestamp = articles.iter()
+                .filter_map(|(_, _, published)| *published)
+                .max()
+                .map(|ts| ts.to_rfc3339());
+            
+            crate::types::SymbolSentiment {
+                symbol: symbol.to_string(),
+                score,
+                article_count: articles.len(),
+                dispersion,
+                latest_timestamp,
+                direction: String::new(),
+            }
+        })
+        .collect();
+    
+    // Strongest conviction first
+    aggregated.sort_by(|a, b| b.score.abs().partial_cmp(&a.score.abs()).unwrap_or(std::cmp::Ordering::Equal));
+    aggregated
+}
+
 // Calculate market summary from trading signals
 pub fn calculate_market_summary(
     trading_signals: &[crate::types::TradingSignal],
     _sentiment_analysis: &[crate::types::SentimentAnalysis],
+    clock: &dyn Clock,
 ) -> crate::types::MarketSummary {
     let total_signals = trading_signals.len();
     let bullish_signals = trading_signals.iter()
@@ -1214,7 +1615,7 @@ pub fn calculate_market_summary(
     let recommended_position_size = calculate_dynamic_position_size(overall_confidence, avg_risk, total_signals);
     
     crate::types::MarketSummary {
-        timestamp: chrono::Utc::now().to_rfc3339(),
+        timestamp: clock.now().to_rfc3339(),
         total_signals,
         bullish_signals,
         bearish_signals,
@@ -1228,11 +1629,22 @@ pub fn calculate_market_summary(
 
 // Calculate portfolio risk metrics
 pub fn calculate_risk_metrics(trading_signals: &[crate::types::TradingSignal]) -> crate::types::RiskMetrics {
+    // Nothing to average over: zeroed metrics rather than NaN
+    if trading_signals.is_empty() {
+        return crate::types::RiskMetrics {
+            portfolio_var: 0.0,
+            max_portfolio_drawdown: 0.0,
+            diversification_score: 0.0,
+            sector_exposure: HashMap::new(),
+            volatility_regime: "LOW".to_string(),
+        };
+    }
+
     let symbols: Vec<String> = trading_signals.iter().map(|s| s.symbol.clone()).collect();
     
-    // Calculate portfolio VaR (simplified)
+    // Calculate portfolio VaR (simplified); signals without an expected return add nothing
     let portfolio_var = trading_signals.iter()
-        .map(|s| s.financial_metrics.var_95 * s.expected_return)
+        .filter_map(|s| s.expected_return.map(|expected| s.financial_metrics.var_95 * expected))
         .sum::<f64>() / trading_signals.len() as f64;
     
     // Calculate max portfolio drawdown
@@ -1266,59 +1678,8 @@ pub fn calculate_risk_metrics(trading_signals: &[crate::types::TradingSignal]) -
     }
 }
 
-// Calculate option Greeks using Black-Scholes approximations
-fn calculate_option_greeks(
-    spot_price: f64,
-    strike_price: f64,
-    implied_volatility: f64,
-    time_to_expiry: f64,
-    is_call: bool,
-) -> (f64, f64, f64, f64) {
-    if time_to_expiry <= 0.0 || implied_volatility <= 0.0 || strike_price <= 0.0 {
-        return (0.0, 0.0, 0.0, 0.0);
-    }
-    
-    let sqrt_t = (time_to_expiry / 365.0).sqrt();
-    let d1 = ((spot_price / strike_price).ln() + 0.5 * implied_volatility * implied_volatility * time_to_expiry / 365.0) 
-             / (implied_volatility * sqrt_t);
-    let d2 = d1 - implied_volatility * sqrt_t;
-    
-    // Normal CDF approximation
-    let n_d1 = 0.5 * (1.0 + erf_approximation(d1 / 1.4142135623730951));
-    let n_d2 = 0.5 * (1.0 + erf_approximation(d2 / 1.4142135623730951));
-    
-    // Normal PDF
-    let phi_d1 = (-0.5 * d1 * d1).exp() / (2.0 * std::f64::consts::PI).sqrt();
-    
-    // Calculate Greeks
-    let delta = if is_call { n_d1 } else { n_d1 - 1.0 };
-    let gamma = phi_d1 / (spot_price * implied_volatility * sqrt_t);
-    let theta = -(spot_price * phi_d1 * implied_volatility) / (2.0 * sqrt_t) 
-                - 0.01 * strike_price * (-0.05 * time_to_expiry / 365.0).exp() * n_d2;
-    let vega = spot_price * phi_d1 * sqrt_t / 100.0; // Per 1% change in IV
-    
-    (delta, gamma, theta, vega)
-}
-
-// Error function approximation for normal CDF
-fn erf_approximation(x: f64) -> f64 {
-    let a1 = 0.254829592;
-    let a2 = -0.284496736;
-    let a3 = 1.421413741;
-    let a4 = -1.453152027;
-    let a5 = 1.061405429;
-    let p = 0.3275911;
-    
-    let sign = if x < 0.0 { -1.0 } else { 1.0 };
-    let x = x.abs();
-    
-    let t = 1.0 / (1.0 + p * x);
-    let y = 1.0 - (((((a5 * t + a4) * t) + a3) * t + a2) * t + a1) * t * (-x * x).exp();
-    
-    sign * y
-}
-
 // Calculate expected option return dynamically
+#[allow(clippy::too_many_arguments)]
 fn calculate_expected_option_return(
     entry_price: f64,
     strike_price: f64,
@@ -1327,7 +1688,7 @@ fn calculate_expected_option_return(
     time_to_expiry: f64,
     is_call: bool,
     volume: u64,
-    open_interest: u64,
+    open_interest: Option<u64>,
 ) -> f64 {
     if entry_price <= 0.0 || time_to_expiry <= 0.0 || spot_price <= 0.0 {
         return 0.0;
@@ -1346,8 +1707,9 @@ fn calculate_expected_option_return(
     // Adjust for time decay
     let time_factor = (time_to_expiry / 30.0).min(1.0); // Favor longer-term options
     
-    // Adjust for liquidity (volume and open interest)
-    let liquidity_factor = ((volume as f64 / 1000.0).min(1.0) + (open_interest as f64 / 1000.0).min(1.0)) / 2.0;
+    // Adjust for liquidity (volume and open interest); unknown open interest earns nothing
+    let open_interest = open_interest.unwrap_or(0) as f64;
+    let liquidity_factor = ((volume as f64 / 1000.0).min(1.0) + (open_interest / 1000.0).min(1.0)) / 2.0;
     
     // Adjust for volatility (higher IV = higher potential return but also higher risk)
     let volatility_factor = (implied_volatility / 0.3).min(2.0); // Cap at 2x normal volatility
@@ -1361,20 +1723,21 @@ fn calculate_expected_option_return(
 
 // Calculate dynamic risk score
 fn calculate_dynamic_risk_score(
-    implied_volatility: f64,
+    implied_volatility: Option<f64>,
     max_drawdown: f64,
     volume: u64,
-    open_interest: u64,
+    open_interest: Option<u64>,
     time_to_expiry: f64,
 ) -> f64 {
-    // Volatility risk (0-0.4)
-    let vol_risk = (implied_volatility / 0.5).min(1.0) * 0.4;
+    // Volatility risk (0-0.4), the full 0.4 when the IV is unknown
+    let vol_risk = implied_volatility.map_or(1.0, |iv| (iv / 0.5).min(1.0)) * 0.4;
     
     // Drawdown risk (0-0.3)
     let drawdown_risk = (max_drawdown / 0.5).min(1.0) * 0.3;
     
-    // Liquidity risk (0-0.2) - lower volume/OI = higher risk
-    let liquidity_risk = (1.0 - ((volume as f64 / 10000.0).min(1.0) + (open_interest as f64 / 10000.0).min(1.0)) / 2.0) * 0.2;
+    // Liquidity risk (0-0.2) - lower volume/OI = higher risk, unknown OI scored as none
+    let open_interest = open_interest.unwrap_or(0) as f64;
+    let liquidity_risk = (1.0 - ((volume as f64 / 10000.0).min(1.0) + (open_interest / 10000.0).min(1.0)) / 2.0) * 0.2;
     
     // Time decay risk (0-0.1) - shorter expiry = higher risk
     let time_risk = (1.0 - (time_to_expiry / 30.0).min(1.0)) * 0.1;
@@ -1382,16 +1745,16 @@ fn calculate_dynamic_risk_score(
     (vol_risk + drawdown_risk + liquidity_risk + time_risk).clamp(0.0, 1.0)
 }
 
-// Calculate dynamic confidence score
+// Calculate dynamic confidence score (sentiment_strength is |net sentiment|)
 fn calculate_dynamic_confidence(
-    sentiment_score: f64,
+    sentiment_strength: f64,
     option_score: f64,
     composite_score: f64,
     volume: u64,
-    open_interest: u64,
+    open_interest: Option<u64>,
 ) -> f64 {
     // Sentiment confidence (0-0.4)
-    let sentiment_confidence = sentiment_score * 0.4;
+    let sentiment_confidence = sentiment_strength.min(1.0) * 0.4;
     
     // Option quality confidence (0-0.3)
     let option_confidence = (option_score / 10.0).min(1.0) * 0.3;
@@ -1399,20 +1762,21 @@ fn calculate_dynamic_confidence(
     // Financial metrics confidence (0-0.2)
     let financial_confidence = (composite_score / 5.0).min(1.0) * 0.2;
     
-    // Liquidity confidence (0-0.1)
-    let liquidity_confidence = ((volume as f64 / 10000.0).min(1.0) + (open_interest as f64 / 10000.0).min(1.0)) / 2.0 * 0.1;
+    // Liquidity confidence (0-0.1), none from an unknown OI
+    let open_interest = open_interest.unwrap_or(0) as f64;
+    let liquidity_confidence = ((volume as f64 / 10000.0).min(1.0) + (open_interest / 10000.0).min(1.0)) / 2.0 * 0.1;
     
     (sentiment_confidence + option_confidence + financial_confidence + liquidity_confidence).clamp(0.0, 1.0)
 }
 
 // Calculate dynamic VaR (95% confidence)
-fn calculate_dynamic_var_95(volatility: f64, mean_return: f64, time_to_expiry: f64) -> f64 {
-    if volatility <= 0.0 || time_to_expiry <= 0.0 {
+fn calculate_dynamic_var_95(volatility: f64, mean_return: f64, trading_days: f64) -> f64 {
+    if volatility <= 0.0 || trading_days <= 0.0 {
         return 0.0;
     }
     
-    // Adjust volatility for time horizon
-    let time_adjusted_vol = volatility * (time_to_expiry / 365.0).sqrt();
+    // Adjust volatility for time horizon (volatility accrues over trading days)
+    let time_adjusted_vol = volatility * (trading_days / 252.0).sqrt();
     
     // Calculate VaR using normal distribution approximation
     // VaR = mean_return - 1.645 * volatility (for 95% confidence)
@@ -1430,13 +1794,13 @@ fn calculate_dynamic_var_95(volatility: f64, mean_return: f64, time_to_expiry: f
 }
 
 // Calculate dynamic Expected Shortfall (Conditional VaR)
-fn calculate_dynamic_expected_shortfall(volatility: f64, mean_return: f64, time_to_expiry: f64) -> f64 {
-    if volatility <= 0.0 || time_to_expiry <= 0.0 {
+fn calculate_dynamic_expected_shortfall(volatility: f64, mean_return: f64, trading_days: f64) -> f64 {
+    if volatility <= 0.0 || trading_days <= 0.0 {
         return 0.0;
     }
     
     // Expected Shortfall is typically 1.2-1.5x VaR for normal distributions
-    let var_95 = calculate_dynamic_var_95(volatility, mean_return, time_to_expiry);
+    let var_95 = calculate_dynamic_var_95(volatility, mean_return, trading_days);
     let es_multiplier = 1.0 + (volatility * 0.5).min(0.5); // Higher volatility = higher ES multiplier
     
     var_95 * es_multiplier
@@ -1457,16 +1821,43 @@ fn calculate_dynamic_downside_deviation(volatility: f64, expected_return: f64, t
     volatility * base_downside_ratio * time_factor
 }
 
-// Get dynamic risk-free rate (simplified - in production, fetch from API)
-fn get_dynamic_risk_free_rate() -> f64 {
-    // In a real implementation, this would fetch current Treasury rates
-    // For now, use a reasonable estimate based on current market conditions
-    let base_rate = 0.045; // 4.5% base rate
-    
-    // Add some variation based on time (simulate market conditions)
-    let time_variation = (chrono::Utc::now().timestamp() % 86400) as f64 / 86400.0 * 0.01; // ±0.5%
-    
-    (base_rate + time_variation).clamp(0.01, 0.08) // Clamp between 1-8%
+const DEFAULT_RISK_FREE_RATE: f64 = 0.045;
+
+// Annual risk-free rate used by the option pricer (decimal)
+fn risk_free_rate() -> f64 {
+    std::env::var("RISK_FREE_RATE")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .unwrap_or(DEFAULT_RISK_FREE_RATE)
+}
+
+const DEFAULT_DIVIDEND_YIELD: f64 = 0.0;
+
+// Continuous dividend yield used by the option pricer (annual, decimal)
+fn dividend_yield() -> f64 {
+    std::env::var("DIVIDEND_YIELD")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .unwrap_or(DEFAULT_DIVIDEND_YIELD)
+}
+
+const DEFAULT_BINOMIAL_STEPS: usize = 201;
+
+// Pricing model for contracts: auto, bsm, crr or leisen_reimer
+fn pricing_model() -> PricingModel {
+    std::env::var("OPTION_PRICING_MODEL")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(PricingModel::Auto)
+}
+
+// Time steps for the binomial pricers
+fn binomial_steps() -> usize {
+    std::env::var("BINOMIAL_STEPS")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|steps| *steps > 0)
+        .unwrap_or(DEFAULT_BINOMIAL_STEPS)
 }
 
 // Calculate dynamic composite score with adaptive weights
@@ -1546,23 +1937,13 @@ fn calculate_dynamic_sector_exposure(symbols: &[String]) -> std::collections::Ha
     sector_exposure
 }
 
-// Calculate time horizon based on expiration date
-fn calculate_time_horizon_from_expiration(expiration_date: &str) -> &'static str {
+// Calculate time horizon based on expiration date, using the LEAP expiry bucket
+fn calculate_time_horizon_from_expiration(expiration_date: &str, clock: &dyn Clock) -> &'static str {
     // Parse the expiration date (format: "YYYY-MM-DD")
-    if let Ok(exp_date) = chrono::NaiveDate::parse_from_str(expiration_date, "%Y-%m-%d") {
-        let current_year = chrono::Utc::now().year();
-        let expiration_year = exp_date.year();
-        
-        // LEAP options: expiration year is not the current year
-        // SHORT_TERM options: expiration year is the current year
-        if expiration_year != current_year {
-            "LEAP"
-        } else {
-            "SHORT_TERM"
-        }
-    } else {
-        // Fallback if date parsing fails
-        "SHORT_TERM"
+    match chrono::NaiveDate::parse_from_str(expiration_date, "%Y-%m-%d") {
+        Ok(exp_date) if DaysToExpiry::until(exp_date, clock).calendar > leap_min_days() => "LEAP",
+        // Fallback to SHORT_TERM if date parsing fails
+        _ => "SHORT_TERM",
     }
 }
 
@@ -1604,3 +1985,187 @@ fn classify_symbol_sector(symbol: &str) -> String {
         "OTHER".to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain_page(contracts: &[&str], next_page_token: Option<&str>) -> OptionChain {
+        OptionChain {
+            snapshots: contracts.iter().map(|c| (c.to_string(), crate::types::OptionSnapshot::default())).collect(),
+            next_page_token: next_page_token.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn chain_pages_are_kept_whole_so_the_token_resumes_after_them() {
+        let mut chain = OptionChain::default();
+        assert!(merge_chain_page(&mut chain, chain_page(&["A1", "A2"], Some("p2")), 10, 3));
+
+        // The contract cap is crossed mid-page: the page is kept whole and fetching stops
+        assert!(!merge_chain_page(&mut chain, chain_page(&["B1", "B2"], Some("p3")), 10, 3));
+        assert_eq!(chain.snapshots.keys().collect::<Vec<_>>(), ["A1", "A2", "B1", "B2"]);
+        assert_eq!((chain.pages_fetched, chain.next_page_token.as_deref(), chain.truncated), (2, Some("p3"), true));
+
+        // Resuming from the token picks up exactly where the chain stopped
+        let mut resumed = OptionChain { next_page_token: chain.next_page_token.clone(), ..Default::default() };
+        assert!(!merge_chain_page(&mut resumed, chain_page(&["C1"], Some("")), 10, 3));
+        assert_eq!(resumed.snapshots.keys().collect::<Vec<_>>(), ["C1"]);
+        assert_eq!((resumed.next_page_token, resumed.truncated), (None, false));
+    }
+
+    #[test]
+    fn chain_fetch_stops_at_the_page_cap() {
+        let mut chain = OptionChain::default();
+        assert!(merge_chain_page(&mut chain, chain_page(&["A1"], Some("p2")), 2, 100));
+        assert!(!merge_chain_page(&mut chain, chain_page(&["B1"], Some("p3")), 2, 100));
+        assert!(chain.truncated);
+        assert_eq!(chain.next_page_token.as_deref(), Some("p3"));
+    }
+
+    #[test]
+    fn risk_metrics_for_no_signals_are_zero_not_nan() {
+        let metrics = calculate_risk_metrics(&[]);
+        assert_eq!(metrics.portfolio_var, 0.0);
+        assert_eq!(metrics.max_portfolio_drawdown, 0.0);
+        assert_eq!(metrics.diversification_score, 0.0);
+        assert!(metrics.sector_exposure.is_empty());
+        assert_eq!(metrics.volatility_regime, "LOW");
+
+        let json = serde_json::to_value(&metrics).unwrap();
+        assert_eq!(json["portfolio_var"], 0.0);
+    }
+
+    #[test]
+    fn strip_html_drops_tags_and_separates_blocks() {
+        assert_eq!(strip_html("<div class=\"lede\">Hello</div><div>World</div>"), "Hello World");
+        assert_eq!(strip_html("<p>Plain <b>bold</b> text</p>"), "Plain bold text");
+        assert_eq!(strip_html("no markup at all"), "no markup at all");
+    }
+
+    #[test]
+    fn strip_html_removes_script_and_style_blocks() {
+        let html = "<p>Before</p><script type=\"text/javascript\">var tag = '<b>';</script>\
+                    <STYLE>p { color: red; }</STYLE><p>After</p>";
+        assert_eq!(strip_html(html), "Before After");
+        // An unclosed script block swallows the rest of the document
+        assert_eq!(strip_html("Kept<script>track()"), "Kept");
+    }
+
+    #[test]
+    fn strip_html_decodes_entities() {
+        assert_eq!(
+            strip_html("AT&amp;T &lt;T&gt; rose&nbsp;5% &ldquo;today&rdquo; &mdash; it&#39;s up"),
+            "AT&T <T> rose 5% \"today\" - it's up"
+        );
+        // Ampersands are decoded last, so double-escaped entities only lose one level
+        assert_eq!(strip_html("&amp;lt;"), "&lt;");
+    }
+
+    #[test]
+    fn strip_html_handles_unclosed_and_literal_angle_brackets() {
+        assert_eq!(strip_html("Shares rose <a href=\"https://exa"), "Shares rose");
+        assert_eq!(strip_html("Trades at P/E < 10, EPS > 2"), "Trades at P/E < 10, EPS > 2");
+        assert_eq!(strip_html("ends with <"), "ends with <");
+    }
+
+    #[test]
+    fn strip_html_keeps_multibyte_text_next_to_tags() {
+        assert_eq!(strip_html("<p>Café</p>€5<br/>naïve<i>日本</i>"), "Café €5 naïve 日本");
+        assert_eq!(strip_html("«<script>x</script>»"), "« »");
+    }
+
+    fn article(symbol: &str, net_score: f64, confidence: f64, created_at: &str) -> crate::types::SentimentAnalysis {
+        crate::types::SentimentAnalysis {
+            headline: String::new(),
+            symbols: vec![symbol.to_string()],
+            sentiment: String::new(),
+            confidence,
+            probabilities: HashMap::new(),
+            net_score,
+            field_results: Vec::new(),
+            created_at: Some(created_at.to_string()),
+        }
+    }
+
+    fn fixed_now() -> chrono::DateTime<chrono::Utc> {
+        crate::clock::FixedClock("2024-03-01T12:00:00Z".parse().unwrap()).now()
+    }
+
+    #[test]
+    fn equal_age_and_confidence_weigh_equally() {
+        let news = [
+            article("AAPL", 0.8, 0.9, "2024-03-01T06:00:00Z"),
+            article("AAPL", -0.2, 0.9, "2024-03-01T06:00:00Z"),
+        ];
+        let aggregated = aggregate_symbol_sentiment(&news, fixed_now(), 24.0);
+        assert_eq!(aggregated.len(), 1);
+        assert_eq!(aggregated[0].article_count, 2);
+        assert!((aggregated[0].score - 0.3).abs() < 1e-12);
+        assert!((aggregated[0].dispersion - 0.5).abs() < 1e-12);
+        assert_eq!(aggregated[0].latest_timestamp.as_deref(), Some("2024-03-01T06:00:00+00:00"));
+    }
+
+    #[test]
+    fn an_article_one_half_life_old_counts_half() {
+        let news = [
+            article("AAPL", 1.0, 0.8, "2024-03-01T12:00:00Z"),
+            article("AAPL", -1.0, 0.8, "2024-02-29T12:00:00Z"),
+        ];
+        // Weights 1 and 0.5: (1 - 0.5) / 1.5
+        let aggregated = aggregate_symbol_sentiment(&news, fixed_now(), 24.0);
+        assert!((aggregated[0].score - 1.0 / 3.0).abs() < 1e-9);
+
+        // Two half-lives: weight 0.25
+        let aggregated = aggregate_symbol_sentiment(&news, fixed_now(), 12.0);
+        assert!((aggregated[0].score - 0.75 / 1.25).abs() < 1e-9);
+
+        // No decay when the half-life is disabled
+        let aggregated = aggregate_symbol_sentiment(&news, fixed_now(), 0.0);
+        assert!(aggregated[0].score.abs() < 1e-12);
+    }
+
+    #[test]
+    fn confidence_scales_each_article() {
+        let news = [
+            article("MSFT", 1.0, 0.9, "2024-03-01T12:00:00Z"),
+            article("MSFT", -1.0, 0.3, "2024-03-01T12:00:00Z"),
+        ];
+        let aggregated = aggregate_symbol_sentiment(&news, fixed_now(), 24.0);
+        assert!((aggregated[0].score - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn unparseable_timestamps_count_as_fresh() {
+        let news = [
+            article("TSLA", 1.0, 0.8, "not a timestamp"),
+            article("TSLA", -1.0, 0.8, "2024-02-29T12:00:00Z"),
+        ];
+        let aggregated = aggregate_symbol_sentiment(&news, fixed_now(), 24.0);
+        assert!((aggregated[0].score - 1.0 / 3.0).abs() < 1e-9);
+        // Only parseable timestamps are reported
+        assert_eq!(aggregated[0].latest_timestamp.as_deref(), Some("2024-02-29T12:00:00+00:00"));
+    }
+
+    #[test]
+    fn future_timestamps_are_not_boosted() {
+        let news = [
+            article("NVDA", 1.0, 0.8, "2024-03-02T12:00:00Z"),
+            article("NVDA", -1.0, 0.8, "2024-03-01T12:00:00Z"),
+        ];
+        let aggregated = aggregate_symbol_sentiment(&news, fixed_now(), 24.0);
+        assert!(aggregated[0].score.abs() < 1e-12);
+    }
+
+    #[test]
+    fn symbols_are_ordered_by_conviction() {
+        let news = [
+            article("AAPL", 0.2, 0.9, "2024-03-01T12:00:00Z"),
+            article("MSFT", -0.7, 0.9, "2024-03-01T12:00:00Z"),
+        ];
+        let aggregated = aggregate_symbol_sentiment(&news, fixed_now(), 24.0);
+        let order: Vec<&str> = aggregated.iter().map(|s| s.symbol.as_str()).collect();
+        assert_eq!(order, ["MSFT", "AAPL"]);
+    }
+}
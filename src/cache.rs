@@ -0,0 +1,203 @@
+use dashmap::DashMap;
+use serde::Serialize;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+// Snapshot of a cache's size and lookup counters
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
+}
+
+impl CacheStats {
+    // Combine the counters of several caches
+    pub fn combine(stats: &[CacheStats]) -> CacheStats {
+        let entries = stats.iter().map(|s| s.entries).sum();
+        let hits = stats.iter().map(|s| s.hits).sum();
+        let misses = stats.iter().map(|s| s.misses).sum();
+        CacheStats { entries, hits, misses, hit_rate: hit_rate(hits, misses) }
+    }
+}
+
+fn hit_rate(hits: u64, misses: u64) -> f64 {
+    let total = hits + misses;
+    if total == 0 { 0.0 } else { hits as f64 / total as f64 }
+}
+
+// Upstream API calls and cache lookups made on behalf of one request, shared by every
+// fetch it makes so concurrent requests never see each other's counts
+#[derive(Debug, Default)]
+pub struct RequestStats {
+    api_calls: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+}
+
+impl RequestStats {
+    // Count one HTTP request sent upstream, retries included
+    pub fn record_api_call(&self) {
+        self.api_calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn api_calls(&self) -> u64 {
+        self.api_calls.load(Ordering::Relaxed)
+    }
+
+    pub fn record_cache_lookup(&self, hit: bool) {
+        let counter = if hit { &self.cache_hits } else { &self.cache_misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn cache_hit_rate(&self) -> f64 {
+        hit_rate(self.cache_hits.load(Ordering::Relaxed), self.cache_misses.load(Ordering::Relaxed))
+    }
+}
+
+// Concurrent cache where every entry carries its own time-to-live
+pub struct TtlCache<V> {
+    entries: DashMap<String, (V, Instant, Duration)>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<V: Clone> TtlCache<V> {
+    pub fn new() -> Self {
+        Self {
+            entries: DashMap::new(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    // Look up a live entry, counting the hit or miss; expired entries are dropped
+    pub fn get(&self, key: &str) -> Option<V> {
+        let value = self.entries.get(key).and_then(|entry| {
+            let (value, inserted, ttl) = entry.value();
+            (inserted.elapsed() < *ttl).then(|| value.clone())
+        });
+
+        match value {
+            Some(value) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(value)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                self.entries.remove_if(key, |_, (_, inserted, ttl)| inserted.elapsed() >= *ttl);
+                None
+            }
+        }
+    }
+
+    // Same as get, also counting the lookup against one request
+    pub fn get_for(&self, key: &str, stats: &RequestStats) -> Option<V> {
+        let value = self.get(key);
+        stats.record_cache_lookup(value.is_some());
+        value
+    }
+
+    pub fn insert(&self, key: String, value: V, ttl: Duration) {
+        self.entries.insert(key, (value, Instant::now(), ttl));
+    }
+
+    // Drop expired entries to keep memory bounded
+    pub fn purge_expired(&self) {
+        self.entries.retain(|_, (_, inserted, ttl)| inserted.elapsed() < *ttl);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        CacheStats {
+            entries: self.entries.len(),
+            hits,
+            misses,
+            hit_rate: hit_rate(hits, misses),
+        }
+    }
+}
+
+impl<V: Clone> Default for TtlCache<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Stable cache key for any serializable query: "{prefix}:{hash}"
+// serde_json maps keep keys sorted, so field order never changes the hash
+pub fn canonical_key<T: Serialize>(prefix: &str, query: &T) -> String {
+    let canonical = serde_json::to_string(&serde_json::to_value(query).unwrap_or_default())
+        .unwrap_or_default();
+    let mut hasher = DefaultHasher::new();
+    canonical.hash(&mut hasher);
+    format!("{}:{:016x}", prefix, hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_expire_after_their_ttl() {
+        let cache = TtlCache::new();
+        cache.insert("live".to_string(), 1, Duration::from_secs(60));
+        cache.insert("expired".to_string(), 2, Duration::ZERO);
+
+        assert_eq!(cache.get("live"), Some(1));
+        assert_eq!(cache.get("expired"), None);
+        assert_eq!(cache.get("missing"), None);
+        // The expired entry is dropped on lookup
+        assert_eq!(cache.stats().entries, 1);
+
+        cache.insert("expired".to_string(), 2, Duration::ZERO);
+        cache.purge_expired();
+        assert_eq!(cache.stats().entries, 1);
+    }
+
+    #[test]
+    fn stats_count_hits_and_misses() {
+        let cache = TtlCache::new();
+        cache.insert("a".to_string(), "value", Duration::from_secs(60));
+        let request = RequestStats::default();
+
+        cache.get_for("a", &request);
+        cache.get_for("a", &request);
+        cache.get_for("b", &request);
+        cache.get("a");
+
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (1, 3, 1));
+        assert!((stats.hit_rate - 0.75).abs() < 1e-12);
+        // Only lookups made through get_for count against the request
+        assert!((request.cache_hit_rate() - 2.0 / 3.0).abs() < 1e-12);
+
+        let combined = CacheStats::combine(&[stats, TtlCache::<u8>::new().stats()]);
+        assert_eq!((combined.hits, combined.misses), (3, 1));
+        assert_eq!(RequestStats::default().cache_hit_rate(), 0.0);
+    }
+
+    #[test]
+    fn canonical_key_ignores_field_order() {
+        #[derive(Serialize)]
+        struct Forward {
+            symbol: &'static str,
+            limit: u32,
+        }
+        #[derive(Serialize)]
+        struct Reversed {
+            limit: u32,
+            symbol: &'static str,
+        }
+
+        let forward = canonical_key("q", &Forward { symbol: "AAPL", limit: 10 });
+        assert_eq!(forward, canonical_key("q", &Reversed { limit: 10, symbol: "AAPL" }));
+        assert_eq!(forward, canonical_key("q", &Forward { symbol: "AAPL", limit: 10 }));
+        assert_ne!(forward, canonical_key("q", &Forward { symbol: "AAPL", limit: 11 }));
+        assert_ne!(forward, canonical_key("other", &Forward { symbol: "AAPL", limit: 10 }));
+        assert!(forward.starts_with("q:"));
+    }
+}
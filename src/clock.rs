@@ -0,0 +1,152 @@
+use chrono::{DateTime, Datelike, NaiveDate, Utc, Weekday};
+use std::sync::Arc;
+
+// Source of "now" for every time-dependent calculation, so runs can be pinned to a date
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    fn now(&self) -> DateTime<Utc>;
+
+    fn today(&self) -> NaiveDate {
+        self.now().date_naive()
+    }
+}
+
+pub type ClockArc = Arc<dyn Clock>;
+
+// Wall-clock time
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+// Always reports the same instant
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+// Parse AS_OF_DATE ("YYYY-MM-DD" or RFC 3339) into a pinned clock
+pub fn clock_from_as_of(as_of: &str) -> Result<ClockArc, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(as_of) {
+        return Ok(Arc::new(FixedClock(timestamp.with_timezone(&Utc))));
+    }
+    let date = NaiveDate::parse_from_str(as_of, "%Y-%m-%d")
+        .map_err(|_| format!("expected YYYY-MM-DD or RFC 3339, got '{as_of}'"))?;
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    Ok(Arc::new(FixedClock(midnight)))
+}
+
+// Calendar and trading days from today until an expiration date
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DaysToExpiry {
+    pub calendar: i64,
+    pub trading: i64,
+}
+
+impl DaysToExpiry {
+    pub fn until(expiration: NaiveDate, clock: &dyn Clock) -> Self {
+        let today = clock.today();
+        Self {
+            calendar: (expiration - today).num_days(),
+            trading: trading_days_between(today, expiration),
+        }
+    }
+
+    // Calendar days, at least one
+    pub fn calendar_days(&self) -> f64 {
+        self.calendar.max(1) as f64
+    }
+
+    // Trading days, at least one
+    pub fn trading_days(&self) -> f64 {
+        self.trading.max(1) as f64
+    }
+}
+
+// Weekdays after `from` up to and including `to`; exchange holidays are not modelled
+pub fn trading_days_between(from: NaiveDate, to: NaiveDate) -> i64 {
+    if to <= from {
+        return 0;
+    }
+    let calendar_days = (to - from).num_days();
+    let full_weeks = calendar_days / 7;
+    let mut trading_days = full_weeks * 5;
+
+    let mut day = from + chrono::Duration::days(full_weeks * 7);
+    while day < to {
+        day = day.succ_opt().unwrap_or(to);
+        if !matches!(day.weekday(), Weekday::Sat | Weekday::Sun) {
+            trading_days += 1;
+        }
+    }
+    trading_days
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn counts_weekdays_after_the_start_date() {
+        // 2024-01-05 is a Friday
+        let friday = date(2024, 1, 5);
+        assert_eq!(trading_days_between(friday, friday), 0);
+        assert_eq!(trading_days_between(friday, date(2024, 1, 6)), 0);
+        assert_eq!(trading_days_between(friday, date(2024, 1, 7)), 0);
+        assert_eq!(trading_days_between(friday, date(2024, 1, 8)), 1);
+        assert_eq!(trading_days_between(friday, date(2024, 1, 12)), 5);
+        assert_eq!(trading_days_between(date(2024, 1, 6), date(2024, 1, 8)), 1);
+        assert_eq!(trading_days_between(friday, date(2024, 2, 2)), 20);
+        // New Year's Day still counts, holidays are not modelled
+        assert_eq!(trading_days_between(date(2023, 12, 29), date(2024, 1, 2)), 2);
+    }
+
+    #[test]
+    fn reversed_range_has_no_trading_days() {
+        assert_eq!(trading_days_between(date(2024, 1, 12), date(2024, 1, 5)), 0);
+    }
+
+    #[test]
+    fn parses_as_of_dates() {
+        let clock = clock_from_as_of("2024-03-15").unwrap();
+        assert_eq!(clock.now().to_rfc3339(), "2024-03-15T00:00:00+00:00");
+
+        // RFC 3339 keeps the time and converts the offset to UTC
+        let clock = clock_from_as_of("2024-03-15T22:30:00-04:00").unwrap();
+        assert_eq!(clock.now().to_rfc3339(), "2024-03-16T02:30:00+00:00");
+        assert_eq!(clock.today(), date(2024, 3, 16));
+
+        for invalid in ["", "2024-13-01", "2024-02-30", "15/03/2024", "2024-03-15 10:00", "yesterday"] {
+            let error = clock_from_as_of(invalid).unwrap_err();
+            assert!(error.contains(invalid), "{invalid}: {error}");
+        }
+    }
+
+    #[test]
+    fn days_to_expiry_follows_the_clock() {
+        // Friday evening: the following Friday is 7 calendar and 5 trading days out
+        let clock = FixedClock(date(2024, 1, 5).and_hms_opt(20, 0, 0).unwrap().and_utc());
+        let days = DaysToExpiry::until(date(2024, 1, 12), &clock);
+        assert_eq!(days, DaysToExpiry { calendar: 7, trading: 5 });
+
+        let today = DaysToExpiry::until(date(2024, 1, 5), &clock);
+        assert_eq!(today, DaysToExpiry { calendar: 0, trading: 0 });
+        assert_eq!(today.calendar_days(), 1.0);
+        assert_eq!(today.trading_days(), 1.0);
+
+        let expired = DaysToExpiry::until(date(2024, 1, 1), &clock);
+        assert_eq!(expired, DaysToExpiry { calendar: -4, trading: 0 });
+        assert_eq!(expired.calendar_days(), 1.0);
+    }
+}
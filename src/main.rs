@@ -1,30 +1,34 @@
 use axum::{
-    extract::State,
+    extract::{Path, Query, State},
     http::{Method, StatusCode},
     response::{IntoResponse, Json},
-    routing::get,
+    routing::{get, post},
     Router,
 };
 use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::Mutex;
 use tower_http::{
     cors::{Any, CorsLayer},
     limit::RequestBodyLimitLayer,
     timeout::TimeoutLayer,
     trace::TraceLayer,
 };
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
 use futures::stream::{self, StreamExt};
-use dashmap::DashMap;
 
 mod alpaca_data;
+mod cache;
+mod clock;
+mod pricing;
 mod types;
+mod vol_surface;
 mod onnx_sentiment;
 
-use types::{TradingBotResponse, SentimentAnalysis, OptionAnalysis, SymbolOptionsAnalysis, TopOption, ExecutionMetadata};
-use onnx_sentiment::{OnnxSentimentModelArc, initialize_onnx_sentiment_model, predict_sentiment_batch};
+use types::{TradingBotResponse, SentimentAnalysis, OptionAnalysis, SymbolOptionsAnalysis, TopOption, ExecutionMetadata, SentimentRequest, SentimentResponse, TextSentiment, TextChunkSentiment, NewsTextSource, FieldSentiment, NeutralSentimentAction, NewsItem, ContractFinancialMetrics, ChainQuery, ChainSortField, ChainContract, OptionsQuery, AnalyzeParams, NewsQuery};
+use cache::{CacheStats, RequestStats, TtlCache};
+use clock::{ClockArc, SystemClock};
+use onnx_sentiment::{ChunkAggregation, OnnxSentimentPoolArc, SentimentResult, initialize_onnx_sentiment_pool, predict_sentiment_batch, predict_sentiment_long};
 
 // Global configuration
 #[derive(Clone, Debug)]
@@ -38,6 +42,17 @@ pub struct AppConfig {
     pub server_port: u16,
     pub request_timeout_secs: u64,
     pub max_text_length: usize,
+    pub max_long_text_length: usize, // Long-text mode and article bodies
+    pub sentiment_pool_size: usize,
+    pub news_text_source: NewsTextSource,
+    pub news_headline_weight: f64,
+    pub news_summary_weight: f64,
+    pub news_body_weight: f64,
+    pub sentiment_half_life_hours: f64,
+    pub sentiment_neutral_band: f64,
+    pub neutral_sentiment_action: NeutralSentimentAction,
+    pub vol_surface: bool, // Fit each symbol's volatility surface unless the request says otherwise
+    pub clock: ClockArc,
 }
 
 impl AppConfig {
@@ -77,6 +92,64 @@ impl AppConfig {
                 .ok()
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(10000),
+            
+            max_long_text_length: onnx_sentiment::max_long_text_length(),
+            
+            sentiment_pool_size: std::env::var("SENTIMENT_POOL_SIZE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(2),
+            
+            news_text_source: std::env::var("NEWS_TEXT_SOURCE")
+                .ok()
+                .map(|s| s.parse())
+                .transpose()
+                .map_err(|e: String| anyhow::anyhow!("Invalid NEWS_TEXT_SOURCE: {}", e))?
+                .unwrap_or(NewsTextSource::Headline),
+            
+            news_headline_weight: std::env::var("NEWS_HEADLINE_WEIGHT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0.5),
+            
+            news_summary_weight: std::env::var("NEWS_SUMMARY_WEIGHT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0.3),
+            
+            news_body_weight: std::env::var("NEWS_BODY_WEIGHT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0.2),
+            
+            sentiment_half_life_hours: std::env::var("SENTIMENT_HALF_LIFE_HOURS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(24.0),
+            
+            sentiment_neutral_band: std::env::var("SENTIMENT_NEUTRAL_BAND")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0.1),
+            
+            neutral_sentiment_action: std::env::var("NEUTRAL_SENTIMENT_ACTION")
+                .ok()
+                .map(|s| s.parse())
+                .transpose()
+                .map_err(|e: String| anyhow::anyhow!("Invalid NEUTRAL_SENTIMENT_ACTION: {}", e))?
+                .unwrap_or(NeutralSentimentAction::Skip),
+            
+            vol_surface: std::env::var("VOL_SURFACE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+            
+            // AS_OF_DATE pins every date calculation, e.g. to replay a past session
+            clock: match std::env::var("AS_OF_DATE") {
+                Ok(as_of) => clock::clock_from_as_of(&as_of)
+                    .map_err(|e| anyhow::anyhow!("Invalid AS_OF_DATE: {}", e))?,
+                Err(_) => Arc::new(SystemClock),
+            },
         };
         
         tracing::info!("Configuration loaded: max_concurrent_requests={}, model_path={}, server={}:{}", 
@@ -91,21 +164,13 @@ impl AppConfig {
 }
 
 
-// Thread-safe sentiment model with lazy initialization
-static ONNX_SENTIMENT_MODEL: Lazy<Mutex<Option<OnnxSentimentModelArc>>> = Lazy::new(|| {
-    Mutex::new(None)
-});
+// Pool of sentiment model sessions, set once at startup
+static ONNX_SENTIMENT_POOL: OnceCell<OnnxSentimentPoolArc> = OnceCell::new();
 
 
-// Global cache for sentiment analysis results
-static SENTIMENT_CACHE: Lazy<DashMap<String, (String, f64, std::time::Instant)>> = Lazy::new(|| {
-    DashMap::new()
-});
-
-// Global cache for options data
-static OPTIONS_CACHE: Lazy<DashMap<String, (serde_json::Value, std::time::Instant)>> = Lazy::new(|| {
-    DashMap::new()
-});
+// Global cache for sentiment analysis results (5 minute TTL)
+static SENTIMENT_CACHE: Lazy<TtlCache<SentimentResult>> = Lazy::new(TtlCache::new);
+const SENTIMENT_CACHE_TTL: Duration = Duration::from_secs(300);
 
 // Application state
 #[derive(Clone)]
@@ -124,6 +189,8 @@ pub enum AppError {
     Internal(String),
     #[error("Configuration error: {0}")]
     Config(String),
+    #[error("Bad request: {0}")]
+    BadRequest(String),
 }
 
 impl IntoResponse for AppError {
@@ -133,6 +200,7 @@ impl IntoResponse for AppError {
                 (StatusCode::INTERNAL_SERVER_ERROR, msg)
             }
             AppError::AlpacaApi(msg) => (StatusCode::BAD_GATEWAY, msg),
+            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
         };
 
         let body = Json(serde_json::json!({
@@ -164,18 +232,16 @@ async fn main() -> anyhow::Result<()> {
     // Initialize configuration
     let config = AppConfig::from_env()?;
     
-    // Initialize ONNX sentiment model
-    tracing::info!("🔄 Initializing ONNX sentiment model...");
-    let onnx_model = initialize_onnx_sentiment_model().await
+    // Initialize ONNX sentiment model pool
+    tracing::info!("🔄 Initializing ONNX sentiment model pool...");
+    let onnx_pool = initialize_onnx_sentiment_pool(config.sentiment_pool_size).await
         .map_err(|e| {
             tracing::error!("❌ Failed to initialize ONNX sentiment model: {}", e);
             e
         })?;
     
-    {
-        let mut model_guard = ONNX_SENTIMENT_MODEL.lock().await;
-        *model_guard = Some(onnx_model);
-    }
+    ONNX_SENTIMENT_POOL.set(onnx_pool)
+        .map_err(|_| anyhow::anyhow!("ONNX sentiment model pool already initialized"))?;
     tracing::info!("✅ ONNX sentiment model initialized successfully");
     
     // Save server config before moving into state
@@ -194,7 +260,10 @@ async fn main() -> anyhow::Result<()> {
     
     // Build our application with routes and middleware
     let app = Router::new()
-        .route("/analyze", get(analyze_endpoint))
+        .route("/analyze", get(analyze_endpoint).post(analyze_body_endpoint))
+        .route("/analyze/:symbol", get(analyze_symbol_endpoint))
+        .route("/options/:symbol/chain", get(option_chain_endpoint))
+        .route("/sentiment", post(sentiment_endpoint))
         .route("/health", get(health_check))
         .route("/metrics", get(metrics_endpoint))
         .layer(TraceLayer::new_for_http())
@@ -208,6 +277,9 @@ async fn main() -> anyhow::Result<()> {
     let listener = tokio::net::TcpListener::bind(&bind_addr).await?;
     tracing::info!("🚀 Server running on http://{}", bind_addr);
     tracing::info!("📊 Analysis endpoint: http://{}/analyze", bind_addr);
+    tracing::info!("🎯 Single-symbol analysis: http://{}/analyze/{{symbol}}", bind_addr);
+    tracing::info!("🔗 Option chain endpoint: http://{}/options/{{symbol}}/chain", bind_addr);
+    tracing::info!("💬 Sentiment endpoint: http://{}/sentiment", bind_addr);
     tracing::info!("❤️  Health check: http://{}/health", bind_addr);
     tracing::info!("📈 Metrics: http://{}/metrics", bind_addr);
     
@@ -218,12 +290,9 @@ async fn main() -> anyhow::Result<()> {
 
 // Health check endpoint
 pub async fn health_check() -> impl IntoResponse {
-    let model_status = {
-        let model_guard = ONNX_SENTIMENT_MODEL.lock().await;
-        match model_guard.as_ref() {
-            Some(_) => "loaded",
-            None => "not_loaded",
-        }
+    let model_status = match ONNX_SENTIMENT_POOL.get() {
+        Some(_) => "loaded",
+        None => "not_loaded",
     };
 
     let health_status = if model_status == "loaded" { "healthy" } else { "unhealthy" };
@@ -256,7 +325,11 @@ pub async fn metrics_endpoint(State(state): State<Arc<AppState>>) -> impl IntoRe
             "server_host": state.config.server_host,
             "server_port": state.config.server_port,
             "max_text_length": state.config.max_text_length,
+            "max_long_text_length": state.config.max_long_text_length,
+            "sentiment_pool_size": state.config.sentiment_pool_size,
+            "news_text_source": state.config.news_text_source,
         },
+        "sentiment_pool": ONNX_SENTIMENT_POOL.get().map(|pool| pool.stats()),
         "system": system_info,
         "timestamp": chrono::Utc::now().to_rfc3339(),
     }))
@@ -278,33 +351,158 @@ fn get_system_metrics() -> serde_json::Value {
                 .as_secs(),
         },
         "cache_stats": {
-            "sentiment_cache_size": SENTIMENT_CACHE.len(),
-            "options_cache_size": OPTIONS_CACHE.len(),
+            "sentiment": SENTIMENT_CACHE.stats(),
+            "options": alpaca_data::OPTIONS_CACHE.stats(),
+            "news": alpaca_data::NEWS_CACHE.stats(),
+            "contracts": alpaca_data::CONTRACTS_CACHE.stats(),
+            "stocks": alpaca_data::STOCK_CACHE.stats(),
+            "overall": total_cache_stats(),
         }
     })
 }
 
 // Clean up expired cache entries to prevent memory leaks
 fn cleanup_expired_cache_entries() {
-    let now = std::time::Instant::now();
+    SENTIMENT_CACHE.purge_expired();
+    alpaca_data::OPTIONS_CACHE.purge_expired();
+    alpaca_data::NEWS_CACHE.purge_expired();
+    alpaca_data::CONTRACTS_CACHE.purge_expired();
+    alpaca_data::STOCK_CACHE.purge_expired();
+}
+
+// Lookup counters summed over every cache
+fn total_cache_stats() -> CacheStats {
+    CacheStats::combine(&[
+        SENTIMENT_CACHE.stats(),
+        alpaca_data::OPTIONS_CACHE.stats(),
+        alpaca_data::NEWS_CACHE.stats(),
+        alpaca_data::CONTRACTS_CACHE.stats(),
+        alpaca_data::STOCK_CACHE.stats(),
+    ])
+}
+
+async fn analyze_endpoint(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<AnalyzeParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let params = resolve_analyze_params(params, state.config.neutral_sentiment_action, state.config.vol_surface)?;
+    run_analysis(&state.config, params).await
+}
+
+// Same as GET /analyze with the parameters in a JSON body
+async fn analyze_body_endpoint(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<AnalyzeParams>,
+) -> Result<impl IntoResponse, AppError> {
+    let params = resolve_analyze_params(params, state.config.neutral_sentiment_action, state.config.vol_surface)?;
+    run_analysis(&state.config, params).await
+}
+
+// Full pipeline for one ticker, driven by news that mentions it. The caller asked for this
+// symbol by name, so neutral or missing news analyzes both sides unless told to skip
+async fn analyze_symbol_endpoint(
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+    Query(mut params): Query<AnalyzeParams>,
+) -> Result<impl IntoResponse, AppError> {
+    if params.symbols.is_some() {
+        return Err(AppError::BadRequest("'symbols' cannot be combined with /analyze/{symbol}".to_string()));
+    }
+    params.symbols = Some(vec![symbol]);
+    run_analysis(&state.config, resolve_analyze_params(params, NeutralSentimentAction::Both, state.config.vol_surface)?).await
+}
+
+// News items scored by /analyze by default, and the most a request may ask for
+const DEFAULT_MAX_NEWS: usize = 50;
+const MAX_NEWS_ITEMS: usize = 500;
+// Longest news lookback /analyze accepts (30 days)
+const MAX_LOOKBACK_HOURS: f64 = 720.0;
+// Signal filters applied when the request does not set them
+const DEFAULT_MIN_CONFIDENCE: f64 = 0.1;
+const DEFAULT_MAX_RISK: f64 = 0.9;
+// Trading signals and top options kept when the request does not set max_signals
+const DEFAULT_MAX_SIGNALS: usize = 10;
+
+// Validate /analyze parameters and fill in defaults, so the echoed values fully describe the run
+fn resolve_analyze_params(
+    params: AnalyzeParams,
+    default_neutral_action: NeutralSentimentAction,
+    default_vol_surface: bool,
+) -> Result<AnalyzeParams, AppError> {
+    let validate_list = |symbols: Option<Vec<String>>| {
+        symbols
+            .map(|symbols| symbols.iter()
+                .filter(|symbol| !symbol.trim().is_empty())
+                .map(|symbol| validate_symbol(symbol))
+                .collect::<Result<Vec<_>, _>>())
+            .transpose()
+    };
+    let symbols = validate_list(params.symbols)?;
+    let exclude_symbols = validate_list(params.exclude_symbols)?;
+    if symbols.as_ref().is_some_and(Vec::is_empty) {
+        return Err(AppError::BadRequest("'symbols' must name at least one symbol".to_string()));
+    }
+    if let (Some(allowed), Some(excluded)) = (&symbols, &exclude_symbols)
+        && let Some(both) = allowed.iter().find(|symbol| excluded.contains(symbol)) {
+        return Err(AppError::BadRequest(format!("'{both}' is in both 'symbols' and 'exclude_symbols'")));
+    }
     
-    // Clean sentiment cache (5 minute TTL)
-    SENTIMENT_CACHE.retain(|_, (_, _, timestamp)| {
-        now.duration_since(*timestamp) < Duration::from_secs(300)
-    });
+    if let Some(hours) = params.lookback_hours
+        && !(hours > 0.0 && hours <= MAX_LOOKBACK_HOURS) {
+        return Err(AppError::BadRequest(format!(
+            "'lookback_hours' must be above 0 and at most {MAX_LOOKBACK_HOURS}, got {hours}"
+        )));
+    }
+    let max_news = params.max_news.unwrap_or(DEFAULT_MAX_NEWS);
+    if !(1..=MAX_NEWS_ITEMS).contains(&max_news) {
+        return Err(AppError::BadRequest(format!("'max_news' must be between 1 and {MAX_NEWS_ITEMS}, got {max_news}")));
+    }
+    let min_confidence = params.min_confidence.unwrap_or(DEFAULT_MIN_CONFIDENCE);
+    let max_risk = params.max_risk.unwrap_or(DEFAULT_MAX_RISK);
+    for (name, value) in [("min_confidence", min_confidence), ("max_risk", max_risk)] {
+        if !(0.0..=1.0).contains(&value) {
+            return Err(AppError::BadRequest(format!("'{name}' must be between 0 and 1, got {value}")));
+        }
+    }
+    let max_signals = params.max_signals.unwrap_or(DEFAULT_MAX_SIGNALS);
+    if max_signals == 0 {
+        return Err(AppError::BadRequest("'max_signals' must be at least 1".to_string()));
+    }
+    let option_type = params.option_type.as_deref().map(str::to_lowercase);
+    if let Some(option_type) = option_type.as_deref()
+        && !matches!(option_type, "call" | "put" | "both") {
+        return Err(AppError::BadRequest(format!("Unknown option type '{option_type}', expected call, put or both")));
+    }
     
-    // Clean options cache (3 minute TTL)
-    OPTIONS_CACHE.retain(|_, (_, timestamp)| {
-        now.duration_since(*timestamp) < Duration::from_secs(180)
-    });
+    Ok(AnalyzeParams {
+        symbols,
+        exclude_symbols,
+        lookback_hours: params.lookback_hours,
+        max_news: Some(max_news),
+        min_confidence: Some(min_confidence),
+        max_risk: Some(max_risk),
+        max_signals: Some(max_signals),
+        option_type,
+        exclude_contentless: Some(params.exclude_contentless.unwrap_or(false)),
+        neutral_sentiment_action: Some(params.neutral_sentiment_action.unwrap_or(default_neutral_action)),
+        vol_surface: Some(params.vol_surface.unwrap_or(default_vol_surface)),
+    })
+}
+
+// Start of a news lookback window, rounded down to the minute so repeated requests share a cache entry
+fn lookback_start(now: chrono::DateTime<chrono::Utc>, hours: f64) -> String {
+    let start = (now.timestamp() - (hours * 3600.0) as i64).div_euclid(60) * 60;
+    chrono::DateTime::from_timestamp(start, 0)
+        .unwrap_or(now)
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
 }
 
-async fn analyze_endpoint(State(state): State<Arc<AppState>>) -> Result<impl IntoResponse, AppError> {
+async fn run_analysis(config: &AppConfig, params: AnalyzeParams) -> Result<axum::response::Response, AppError> {
     let start_time = std::time::Instant::now();
     
-    tracing::info!("📊 Starting sentiment analysis request");
+    tracing::info!(parameters = ?params, "📊 Starting sentiment analysis request");
     
-    match perform_analysis(&state.config).await {
+    match perform_analysis(config, params).await {
         Ok(mut response) => {
             // Update execution metadata with actual timing
             response.execution_metadata.processing_time_ms = start_time.elapsed().as_millis().min(u64::MAX as u128) as u64;
@@ -330,112 +528,558 @@ async fn analyze_endpoint(State(state): State<Arc<AppState>>) -> Result<impl Int
     }
 }
 
-#[allow(clippy::too_many_lines)]
-async fn perform_analysis(config: &AppConfig) -> anyhow::Result<TradingBotResponse> {
-    // Get news and filter headlines with symbols
-    let input = alpaca_data::get_alpaca_news().await
-        .map_err(|e| anyhow::anyhow!("Alpaca API error: {}", e))?;
+async fn option_chain_endpoint(
+    State(state): State<Arc<AppState>>,
+    Path(symbol): Path<String>,
+    Query(params): Query<ChainQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let symbol = validate_symbol(&symbol)?;
+    let options_query = chain_options_query(&params)?;
+    let sort = params.sort.as_deref()
+        .map(str::parse::<ChainSortField>)
+        .transpose()
+        .map_err(AppError::BadRequest)?
+        .unwrap_or(ChainSortField::Expiration);
+    let descending = match params.order.as_deref().map(str::to_lowercase).as_deref() {
+        None | Some("asc") => false,
+        Some("desc") => true,
+        Some(other) => return Err(AppError::BadRequest(format!("Unknown order '{other}', expected asc or desc"))),
+    };
+    if params.limit == Some(0) {
+        return Err(AppError::BadRequest("'limit' must be at least 1".to_string()));
+    }
+    if params.max_spread_pct.is_some_and(|max| max < 0.0) {
+        return Err(AppError::BadRequest("'max_spread_pct' must not be negative".to_string()));
+    }
     
-    let news_array = input["news"].as_array()
-        .ok_or_else(|| anyhow::anyhow!("Expected 'news' array from Alpaca API"))?;
+    let mut chain = alpaca_data::fetch_normalized_chain(&symbol, &options_query, state.config.clock.as_ref(), &RequestStats::default()).await
+        .map_err(AppError::AlpacaApi)?;
     
-    // Filter news with symbols and collect headlines
-    let mut news_with_symbols = Vec::new();
-    let mut headlines = Vec::new();
-    
-    for item in news_array {
-        if let Some(symbols) = item["symbols"].as_array() {
-            if !symbols.is_empty() {
-                let headline = item["headline"].as_str().unwrap_or("");
-                let symbols_vec: Vec<String> = symbols.iter()
-                    .filter_map(|s| s.as_str())
-                    .map(std::string::ToString::to_string)
-                    .collect();
-                
-                news_with_symbols.push((headline.to_string(), symbols_vec));
-                headlines.push(headline);
-            }
+    chain.contracts.retain(|contract| {
+        params.min_volume.is_none_or(|min| contract.volume >= min)
+            && params.min_open_interest.is_none_or(|min| contract.open_interest.unwrap_or(0) >= min)
+            && params.max_spread_pct.is_none_or(|max| contract.spread_pct.is_some_and(|spread| spread <= max))
+    });
+    sort_chain(&mut chain.contracts, sort, descending);
+    if let Some(limit) = params.limit {
+        chain.contracts.truncate(limit);
+    }
+    chain.returned_contracts = chain.contracts.len();
+    
+    tracing::info!(
+        symbol = %symbol,
+        total = chain.total_contracts,
+        returned = chain.returned_contracts,
+        truncated = chain.truncated,
+        "✅ Option chain served"
+    );
+    
+    Ok((StatusCode::OK, Json(chain)).into_response())
+}
+
+// Upper-cased ticker, or a 400 for anything that cannot be one
+fn validate_symbol(symbol: &str) -> Result<String, AppError> {
+    let symbol = symbol.trim().to_uppercase();
+    if symbol.is_empty()
+        || symbol.len() > 10
+        || !symbol.chars().all(|c| c.is_ascii_alphanumeric() || c == '.') {
+        return Err(AppError::BadRequest(format!("Invalid symbol '{symbol}'")));
+    }
+    Ok(symbol)
+}
+
+// Pagination caps for a chain request bounded by expiry, above the OPTIONS_MAX_* defaults
+// since the date filter already keeps the fetch finite
+const NARROWED_CHAIN_MAX_PAGES: u32 = 20;
+const NARROWED_CHAIN_MAX_CONTRACTS: usize = 10_000;
+
+// Map chain endpoint parameters onto the Alpaca snapshot filters, walking every page
+fn chain_options_query(params: &ChainQuery) -> Result<OptionsQuery, AppError> {
+    let option_type = params.r#type.as_deref().map(str::to_lowercase);
+    if let Some(option_type) = option_type.as_deref()
+        && !matches!(option_type, "call" | "put") {
+        return Err(AppError::BadRequest(format!("Unknown option type '{option_type}', expected call or put")));
+    }
+    for (name, strike) in [("strike_price_gte", params.strike_price_gte), ("strike_price_lte", params.strike_price_lte)] {
+        if strike.is_some_and(|strike| strike <= 0.0) {
+            return Err(AppError::BadRequest(format!("'{name}' must be positive")));
         }
     }
+    if let (Some(low), Some(high)) = (params.strike_price_gte, params.strike_price_lte)
+        && low > high {
+        return Err(AppError::BadRequest("'strike_price_gte' is above 'strike_price_lte'".to_string()));
+    }
     
-    // Run sentiment analysis with ONNX model and caching
-    let sentiments = {
-        let model_guard = ONNX_SENTIMENT_MODEL.lock().await;
-        let model_arc = model_guard
-            .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("ONNX sentiment model not initialized"))?;
-        
-        // Check cache first and batch uncached headlines
-        let mut cached_results = Vec::new();
-        let mut uncached_headlines = Vec::new();
-        let mut uncached_indices = Vec::new();
-        
-        for (i, headline) in headlines.iter().enumerate() {
-            let cache_key = format!("sentiment:{}", headline);
-            if let Some(entry) = SENTIMENT_CACHE.get(&cache_key) {
-                let (sentiment, confidence, timestamp) = entry.value();
-                // Check if cache entry is still valid (5 minutes)
-                if timestamp.elapsed() < Duration::from_secs(300) {
-                    cached_results.push((i, sentiment.clone(), *confidence));
-                    continue;
-                }
-            }
-            uncached_headlines.push(headline.to_string());
-            uncached_indices.push(i);
+    let parse_date = |name: &str, value: &Option<String>| {
+        value.as_deref()
+            .map(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| AppError::BadRequest(format!("'{name}' must be YYYY-MM-DD, got '{date}'"))))
+            .transpose()
+    };
+    parse_date("expiration_date", &params.expiration_date)?;
+    let from = parse_date("expiration_date_gte", &params.expiration_date_gte)?;
+    let to = parse_date("expiration_date_lte", &params.expiration_date_lte)?;
+    if let (Some(from), Some(to)) = (from, to)
+        && from > to {
+        return Err(AppError::BadRequest("'expiration_date_gte' is after 'expiration_date_lte'".to_string()));
+    }
+    let bounded_by_expiry = params.expiration_date.is_some() || to.is_some();
+    
+    Ok(OptionsQuery {
+        feed: params.feed.clone(),
+        r#type: option_type,
+        alpaca_limit: Some(1000), // Largest page the snapshots endpoint returns
+        strike_price_gte: params.strike_price_gte,
+        strike_price_lte: params.strike_price_lte,
+        expiration_date: params.expiration_date.clone(),
+        expiration_date_gte: params.expiration_date_gte.clone(),
+        expiration_date_lte: params.expiration_date_lte.clone(),
+        page_token: params.page_token.clone(),
+        fetch_all: Some(true),
+        max_pages: bounded_by_expiry.then_some(NARROWED_CHAIN_MAX_PAGES),
+        max_contracts: bounded_by_expiry.then_some(NARROWED_CHAIN_MAX_CONTRACTS),
+        ..Default::default()
+    })
+}
+
+// Sort by the requested field with contracts missing it last, then by expiration and strike
+fn sort_chain(contracts: &mut [ChainContract], sort: ChainSortField, descending: bool) {
+    let key = |contract: &ChainContract| -> Option<f64> {
+        match sort {
+            ChainSortField::Expiration => Some(contract.days_to_expiry as f64),
+            ChainSortField::Strike => Some(contract.strike_price),
+            ChainSortField::Volume => Some(contract.volume as f64),
+            ChainSortField::OpenInterest => contract.open_interest.map(|oi| oi as f64),
+            ChainSortField::ImpliedVolatility => contract.implied_volatility,
+            ChainSortField::SpreadPct => contract.spread_pct,
+            ChainSortField::Delta => contract.greeks.as_ref().map(|g| g.delta),
+            ChainSortField::Mid => contract.mid,
         }
-        
-        // Predict uncached headlines
-        let uncached_sentiments = if !uncached_headlines.is_empty() {
-            predict_sentiment_batch(model_arc, &uncached_headlines).await
-                .map_err(|e| anyhow::anyhow!("ONNX sentiment analysis failed: {}", e))?
-        } else {
-            Vec::new()
+    };
+    contracts.sort_by(|a, b| {
+        let primary = match (key(a), key(b)) {
+            (Some(x), Some(y)) if descending => y.total_cmp(&x),
+            (Some(x), Some(y)) => x.total_cmp(&y),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
         };
-        
-        // Cache new results
-        for (sentiment, headline) in uncached_sentiments.iter().zip(uncached_headlines.iter()) {
-            let cache_key = format!("sentiment:{}", headline);
-            SENTIMENT_CACHE.insert(cache_key, (sentiment.sentiment.clone(), sentiment.confidence, std::time::Instant::now()));
+        primary
+            .then_with(|| a.expiration_date.cmp(&b.expiration_date))
+            .then_with(|| a.strike_price.total_cmp(&b.strike_price))
+    });
+}
+
+// Sentiment results in input order, flagged by whether they came from SENTIMENT_CACHE
+struct CachedSentiments {
+    results: Vec<SentimentResult>,
+    cache_hits: Vec<bool>,
+    inference_ms: u64,
+}
+
+// Score texts with the ONNX model, serving repeats from SENTIMENT_CACHE
+async fn predict_sentiment_cached(texts: &[String], stats: &RequestStats) -> anyhow::Result<CachedSentiments> {
+    let model_pool = ONNX_SENTIMENT_POOL
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("ONNX sentiment model not initialized"))?;
+    
+    // Check cache first and batch uncached texts
+    let mut cached_results = Vec::new();
+    let mut uncached_texts = Vec::new();
+    let mut uncached_indices = Vec::new();
+    
+    for (i, text) in texts.iter().enumerate() {
+        let cache_key = format!("sentiment:{}", text);
+        if let Some(sentiment) = SENTIMENT_CACHE.get_for(&cache_key, stats) {
+            cached_results.push((i, sentiment));
+            continue;
+        }
+        uncached_texts.push(text.clone());
+        uncached_indices.push(i);
+    }
+    
+    // Predict uncached texts
+    let inference_start = std::time::Instant::now();
+    let uncached_sentiments = if !uncached_texts.is_empty() {
+        predict_sentiment_batch(model_pool, &uncached_texts).await
+            .map_err(|e| anyhow::anyhow!("ONNX sentiment analysis failed: {}", e))?
+    } else {
+        Vec::new()
+    };
+    let inference_ms = inference_start.elapsed().as_millis().min(u64::MAX as u128) as u64;
+    
+    // Cache new results
+    for (sentiment, text) in uncached_sentiments.iter().zip(uncached_texts.iter()) {
+        let cache_key = format!("sentiment:{}", text);
+        SENTIMENT_CACHE.insert(cache_key, sentiment.clone(), SENTIMENT_CACHE_TTL);
+    }
+    
+    // Combine cached and new results in correct order
+    let mut results = vec![SentimentResult::neutral(); texts.len()];
+    let mut cache_hits = vec![false; texts.len()];
+    
+    // Insert cached results
+    for (i, sentiment) in cached_results {
+        results[i] = sentiment;
+        cache_hits[i] = true;
+    }
+    
+    // Insert new results
+    for (sentiment, i) in uncached_sentiments.into_iter().zip(uncached_indices) {
+        results[i] = sentiment;
+    }
+    
+    Ok(CachedSentiments {
+        results,
+        cache_hits,
+        inference_ms,
+    })
+}
+
+// Maximum number of texts accepted by a single /sentiment request
+const MAX_SENTIMENT_TEXTS: usize = 100;
+
+async fn sentiment_endpoint(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SentimentRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let start_time = std::time::Instant::now();
+    
+    if request.texts.is_empty() {
+        return Err(AppError::BadRequest("'texts' must contain at least one entry".to_string()));
+    }
+    if request.texts.len() > MAX_SENTIMENT_TEXTS {
+        return Err(AppError::BadRequest(format!(
+            "Too many texts ({}), maximum is {}", request.texts.len(), MAX_SENTIMENT_TEXTS
+        )));
+    }
+    
+    let aggregation = request.aggregation.as_deref()
+        .map(str::parse::<ChunkAggregation>)
+        .transpose()
+        .map_err(|e| AppError::BadRequest(e.to_string()))?;
+    
+    // Long-text mode reads the whole text through sliding windows, so it takes much longer input
+    let max_length = if aggregation.is_some() {
+        state.config.max_long_text_length
+    } else {
+        state.config.max_text_length
+    };
+    for (i, input) in request.texts.iter().enumerate() {
+        let label = input.id.clone().unwrap_or_else(|| i.to_string());
+        if input.text.trim().is_empty() {
+            return Err(AppError::BadRequest(format!("Text '{}' is empty", label)));
+        }
+        if input.text.len() > max_length {
+            return Err(AppError::BadRequest(format!(
+                "Text '{}' is too long ({} characters, max {})",
+                label, input.text.len(), max_length
+            )));
         }
+    }
+    
+    let results: Vec<TextSentiment> = if let Some(aggregation) = aggregation {
+        score_long_texts(request, aggregation).await?
+    } else {
+        let texts: Vec<String> = request.texts.iter().map(|input| input.text.clone()).collect();
+        let scored = predict_sentiment_cached(&texts, &RequestStats::default()).await
+            .map_err(|e| AppError::SentimentAnalysis(e.to_string()))?;
         
-        // Combine cached and new results in correct order
-        let mut all_sentiments = vec![onnx_sentiment::SentimentResult { sentiment: "neutral".to_string(), confidence: 0.5 }; headlines.len()];
+        request.texts.into_iter()
+            .zip(scored.results)
+            .zip(scored.cache_hits)
+            .map(|((input, sentiment), cached)| TextSentiment {
+                id: input.id,
+                sentiment: sentiment.sentiment,
+                confidence: sentiment.confidence,
+                probabilities: sentiment.probabilities,
+                net_score: sentiment.net_score,
+                cached,
+                latency_ms: if cached { 0 } else { scored.inference_ms },
+                chunks: None,
+            })
+            .collect()
+    };
+    
+    let processing_time_ms = start_time.elapsed().as_millis().min(u64::MAX as u128) as u64;
+    tracing::info!(
+        texts = results.len(),
+        duration_ms = processing_time_ms,
+        "✅ Sentiment scoring completed"
+    );
+    
+    Ok((StatusCode::OK, Json(SentimentResponse { results, processing_time_ms })).into_response())
+}
+
+// Score each text with overlapping model windows, bypassing the headline cache
+async fn score_long_texts(request: SentimentRequest, aggregation: ChunkAggregation) -> Result<Vec<TextSentiment>, AppError> {
+    let model_pool = ONNX_SENTIMENT_POOL
+        .get()
+        .ok_or_else(|| AppError::SentimentAnalysis("ONNX sentiment model not initialized".to_string()))?;
+    
+    let mut results = Vec::with_capacity(request.texts.len());
+    for input in request.texts {
+        let start_time = std::time::Instant::now();
+        let long_result = predict_sentiment_long(model_pool, &input.text, aggregation).await
+            .map_err(|e| AppError::SentimentAnalysis(e.to_string()))?;
         
-        // Insert cached results
-        for (i, sentiment, confidence) in cached_results {
-            all_sentiments[i] = onnx_sentiment::SentimentResult { sentiment, confidence };
-        }
+        let chunks = long_result.chunks.into_iter()
+            .map(|chunk| TextChunkSentiment {
+                start_offset: chunk.start_offset,
+                end_offset: chunk.end_offset,
+                token_count: chunk.token_count,
+                sentiment: chunk.result.sentiment,
+                confidence: chunk.result.confidence,
+                net_score: chunk.result.net_score,
+            })
+            .collect();
         
-        // Insert new results
-        for (sentiment, i) in uncached_sentiments.iter().zip(uncached_indices) {
-            all_sentiments[i] = sentiment.clone();
+        results.push(TextSentiment {
+            id: input.id,
+            sentiment: long_result.document.sentiment,
+            confidence: long_result.document.confidence,
+            probabilities: long_result.document.probabilities,
+            net_score: long_result.document.net_score,
+            cached: false,
+            latency_ms: start_time.elapsed().as_millis().min(u64::MAX as u128) as u64,
+            chunks: Some(chunks),
+        });
+    }
+    
+    Ok(results)
+}
+
+// Pick the news fields to score for the configured source, with their blend weights.
+// Missing fields fall back to the next shorter one so every item gets a score.
+fn select_news_fields(item: &NewsItem, headline: &str, config: &AppConfig) -> Vec<(NewsTextSource, String, f64)> {
+    let clean = |field: &str, max_length: usize| {
+        truncate_to_char_boundary(&alpaca_data::strip_html(field), max_length).to_string()
+    };
+    let summary = clean(&item.summary, config.max_text_length);
+    // Bodies are scored in long-text mode
+    let body = clean(&item.content, config.max_long_text_length);
+    
+    let candidates = [
+        (NewsTextSource::Headline, headline.to_string(), config.news_headline_weight),
+        (NewsTextSource::Summary, summary, config.news_summary_weight),
+        (NewsTextSource::Body, body, config.news_body_weight),
+    ];
+    let available = candidates.into_iter().filter(|(_, text, _)| !text.is_empty());
+    
+    match config.news_text_source {
+        NewsTextSource::Combined => available.filter(|(_, _, weight)| *weight > 0.0).collect(),
+        source => {
+            // Preferred field first, then shorter fields as fallback
+            let order: &[NewsTextSource] = match source {
+                NewsTextSource::Body => &[NewsTextSource::Body, NewsTextSource::Summary, NewsTextSource::Headline],
+                NewsTextSource::Summary => &[NewsTextSource::Summary, NewsTextSource::Headline],
+                _ => &[NewsTextSource::Headline],
+            };
+            let available: Vec<_> = available.collect();
+            order.iter()
+                .find_map(|wanted| available.iter().find(|(field, _, _)| field == wanted))
+                .map(|(field, text, _)| vec![(*field, text.clone(), 1.0)])
+                .unwrap_or_default()
+        }
+    }
+}
+
+fn truncate_to_char_boundary(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+// Score article bodies with overlapping model windows, caching the document-level result
+async fn predict_long_sentiment_cached(texts: &[String], config: &AppConfig, stats: &RequestStats) -> anyhow::Result<Vec<SentimentResult>> {
+    let model_pool = ONNX_SENTIMENT_POOL
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("ONNX sentiment model not initialized"))?;
+    
+    let futures = texts.iter().cloned().map(|text| async move {
+        let cache_key = format!("sentiment:long:{}", text);
+        if let Some(sentiment) = SENTIMENT_CACHE.get_for(&cache_key, stats) {
+            return Ok(sentiment);
         }
         
-        all_sentiments
+        let result = predict_sentiment_long(model_pool, &text, ChunkAggregation::AttentionWeighted).await
+            .map_err(|e| anyhow::anyhow!("ONNX sentiment analysis failed: {}", e))?;
+        SENTIMENT_CACHE.insert(cache_key, result.document.clone(), SENTIMENT_CACHE_TTL);
+        Ok::<_, anyhow::Error>(result.document)
+    });
+    
+    // Keep every pooled session busy without queueing the whole batch at once
+    stream::iter(futures)
+        .buffered(config.sentiment_pool_size.max(1))
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect()
+}
+
+// "call", "put" or "neutral" for a net sentiment score
+fn sentiment_direction(score: f64, neutral_band: f64) -> &'static str {
+    if score > neutral_band {
+        "call"
+    } else if score < -neutral_band {
+        "put"
+    } else {
+        "neutral"
+    }
+}
+
+// Option sides to fetch for a symbol; empty when a neutral symbol should be skipped
+fn option_sides_for_sentiment(score: f64, neutral_band: f64, neutral_action: NeutralSentimentAction) -> Vec<&'static str> {
+    match (sentiment_direction(score, neutral_band), neutral_action) {
+        ("call", _) => vec!["call"],
+        ("put", _) => vec!["put"],
+        (_, NeutralSentimentAction::Both) => vec!["call", "put"],
+        (_, NeutralSentimentAction::Skip) => Vec::new(),
+    }
+}
+
+// Attach financial metrics to every analyzed contract
+fn attach_financial_metrics(options: &mut [OptionAnalysis], clock: &dyn clock::Clock) {
+    for option in options {
+        let implied_volatility = alpaca_data::resolve_implied_volatility(&option.contract, clock).value;
+        option.contract.financial_metrics = alpaca_data::calculate_option_financial_metrics(&option.contract, implied_volatility, clock)
+            .map(|metrics| ContractFinancialMetrics {
+                sharpe_ratio: metrics.sharpe,
+                sortino_ratio: metrics.sortino,
+                calmar_ratio: metrics.calmar,
+                max_drawdown: metrics.max_drawdown,
+                volatility: metrics.volatility,
+                composite_score: metrics.composite_score,
+                kelly_fraction: metrics.kelly_fraction,
+            });
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+// `params` must come from resolve_analyze_params, which fills in every default
+async fn perform_analysis(config: &AppConfig, params: AnalyzeParams) -> anyhow::Result<TradingBotResponse> {
+    let request_stats = RequestStats::default();
+    
+    // Article bodies are only returned by Alpaca when explicitly requested
+    let needs_content = matches!(config.news_text_source, NewsTextSource::Body | NewsTextSource::Combined);
+    
+    // Get news and filter items with symbols
+    let news_query = NewsQuery {
+        symbols: params.symbols.clone().unwrap_or_default(),
+        start: params.lookback_hours.map(|hours| lookback_start(config.clock.now(), hours)),
+        end: None,
+        limit: params.max_news.unwrap_or(DEFAULT_MAX_NEWS),
+        include_content: needs_content,
+        exclude_contentless: params.exclude_contentless.unwrap_or(false),
     };
+    let input = alpaca_data::get_alpaca_news(&news_query, &request_stats).await
+        .map_err(|e| anyhow::anyhow!("Alpaca API error: {}", e))?;
+    
+    // Symbols the request allows; articles often tag others too
+    let symbol_allowed = |symbol: &str| {
+        params.symbols.as_ref().is_none_or(|allowed| allowed.iter().any(|s| s == symbol))
+            && !params.exclude_symbols.as_ref().is_some_and(|excluded| excluded.iter().any(|s| s == symbol))
+    };
+    
+    // Filter news with symbols and collect the text fields to score
+    let mut news_with_symbols = Vec::new();
+    
+    for item in &input.news {
+        if item.symbols.iter().any(|symbol| symbol_allowed(symbol)) {
+            let headline = item.headline.trim().to_string();
+            let fields = select_news_fields(item, &headline, config);
+            news_with_symbols.push((headline, item.symbols.clone(), fields, item.created_at.clone()));
+        }
+    }
+    
+    // Short fields go through the cached batch path, bodies through long-text windows
+    let short_texts: Vec<String> = news_with_symbols.iter()
+        .flat_map(|(_, _, fields, _)| fields.iter())
+        .filter(|(field, _, _)| *field != NewsTextSource::Body)
+        .map(|(_, text, _)| text.clone())
+        .collect();
+    let body_texts: Vec<String> = news_with_symbols.iter()
+        .flat_map(|(_, _, fields, _)| fields.iter())
+        .filter(|(field, _, _)| *field == NewsTextSource::Body)
+        .map(|(_, text, _)| text.clone())
+        .collect();
     
-    // Create sentiment analysis results
-    let mut sentiment_results: Vec<_> = news_with_symbols.iter().zip(sentiments.iter()).map(|((headline, symbols), sentiment)| {
-        SentimentAnalysis {
+    // Run sentiment analysis with ONNX model and caching
+    let mut short_sentiments = predict_sentiment_cached(&short_texts, &request_stats).await?.results.into_iter();
+    let mut body_sentiments = predict_long_sentiment_cached(&body_texts, config, &request_stats).await?.into_iter();
+    
+    // Reassemble per-field results and blend them into one score per item
+    let mut sentiment_results = Vec::with_capacity(news_with_symbols.len());
+    
+    for (headline, symbols, fields, created_at) in news_with_symbols.iter() {
+        let field_sentiments: Vec<(NewsTextSource, f64, SentimentResult)> = fields.iter()
+            .filter_map(|(field, _, weight)| {
+                let sentiment = if *field == NewsTextSource::Body {
+                    body_sentiments.next()
+                } else {
+                    short_sentiments.next()
+                };
+                sentiment.map(|sentiment| (*field, *weight, sentiment))
+            })
+            .collect();
+        
+        let parts: Vec<(f64, &SentimentResult)> = field_sentiments.iter()
+            .map(|(_, weight, sentiment)| (*weight, sentiment))
+            .collect();
+        let sentiment = match parts.as_slice() {
+            [(_, only)] => (*only).clone(),
+            _ => SentimentResult::weighted_average(&parts),
+        };
+        
+        let field_results = field_sentiments.iter()
+            .map(|(field, weight, result)| FieldSentiment {
+                field: *field,
+                sentiment: result.sentiment.clone(),
+                confidence: result.confidence,
+                net_score: result.net_score,
+                weight: *weight,
+            })
+            .collect();
+        
+        sentiment_results.push(SentimentAnalysis {
             headline: headline.clone(),
             symbols: symbols.clone(),
             sentiment: sentiment.sentiment.clone(),
             confidence: sentiment.confidence,
-        }
-    }).collect();
+            probabilities: sentiment.probabilities.clone(),
+            net_score: sentiment.net_score,
+            field_results,
+            created_at: created_at.clone(),
+        });
+    }
     
     // Sort news analysis by confidence (highest to lowest)
     sentiment_results.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap_or(std::cmp::Ordering::Equal));
     
-    // Count sentiments (for potential future use)
-    let _positive_count = sentiments.iter().filter(|s| s.sentiment == "positive").count();
-    let _negative_count = sentiments.iter().filter(|s| s.sentiment == "negative").count();
-    
     let news_analysis = sentiment_results;
     
-    // Deduplicate symbols efficiently and filter out crypto
+    // Combine every article per symbol, weighted by confidence and recency
+    let mut symbol_sentiment = alpaca_data::aggregate_symbol_sentiment(
+        &news_analysis,
+        config.clock.now(),
+        config.sentiment_half_life_hours,
+    );
+    symbol_sentiment.retain(|entry| symbol_allowed(&entry.symbol));
+    for entry in &mut symbol_sentiment {
+        entry.direction = sentiment_direction(entry.score, config.sentiment_neutral_band).to_string();
+    }
+    
+    // Deduplicate symbols efficiently and filter out crypto; requested symbols are analyzed
+    // even when no article mentions them
     let all_symbols: HashSet<String> = news_with_symbols.iter()
-        .flat_map(|(_, symbols)| symbols.iter())
+        .flat_map(|(_, symbols, _, _)| symbols.iter())
+        .chain(params.symbols.iter().flatten())
+        .filter(|symbol| symbol_allowed(symbol))
         .cloned()
         .collect();
     
@@ -448,111 +1092,109 @@ async fn perform_analysis(config: &AppConfig) -> anyhow::Result<TradingBotRespon
         .filter(|symbol| !alpaca_data::is_crypto_symbol(symbol))
         .collect();
     
-    println!("Filtered out {} crypto symbols: {:?}", crypto_symbols.len(), crypto_symbols);
+    tracing::debug!("Filtered out {} crypto symbols: {:?}", crypto_symbols.len(), crypto_symbols);
     
     // Analyze options for unique symbols in parallel
-    // Calculate weighted overall sentiment based on confidence scores
-    let (positive_weight, negative_weight) = sentiments.iter()
-        .fold((0.0, 0.0), |(pos, neg), sentiment| {
-            match sentiment.sentiment.as_str() {
-                "positive" => (pos + sentiment.confidence, neg),
-                "negative" => (pos, neg + sentiment.confidence),
-                _ => (pos, neg), // neutral sentiments don't contribute
+    // Choose option sides per symbol from that symbol's own aggregated sentiment; symbols
+    // without news count as neutral
+    let neutral_action = params.neutral_sentiment_action.unwrap_or(config.neutral_sentiment_action);
+    let mut skipped_symbols = Vec::new();
+    let symbol_sides: Vec<(String, Vec<&'static str>)> = unique_symbols_vec.iter()
+        .filter_map(|symbol| {
+            let sentiment = symbol_sentiment.iter().find(|s| &s.symbol == symbol);
+            let score = sentiment.map(|s| s.score).unwrap_or(0.0);
+            let sides = match params.option_type.as_deref() {
+                Some("call") => vec!["call"],
+                Some("put") => vec!["put"],
+                Some("both") => vec!["call", "put"],
+                _ => option_sides_for_sentiment(score, config.sentiment_neutral_band, neutral_action),
+            };
+            if sides.is_empty() {
+                tracing::debug!(symbol = %symbol, score, "Skipping symbol with neutral sentiment");
+                let reason = match sentiment {
+                    Some(_) => format!(
+                        "Skipped: net sentiment {score:+.2} is inside the neutral band (±{:.2}); set neutral_sentiment_action=both to analyze it anyway",
+                        config.sentiment_neutral_band,
+                    ),
+                    None => "Skipped: no news mentions this symbol; set neutral_sentiment_action=both to analyze it anyway".to_string(),
+                };
+                skipped_symbols.push(SymbolOptionsAnalysis {
+                    symbol: symbol.clone(),
+                    underlying_metrics: serde_json::json!({}),
+                    options_analysis: Vec::new(),
+                    error: Some(reason),
+                });
+                None
+            } else {
+                Some((symbol.clone(), sides))
             }
-        });
+        })
+        .collect();
     
-    let overall_sentiment = if positive_weight > negative_weight * 1.2 {
-        "call"
-    } else if negative_weight > positive_weight * 1.2 {
-        "put"
-    } else {
-        // Mixed sentiment - use a more nuanced approach
-        if positive_weight > negative_weight {
-            "call"
-        } else {
-            "put"
-        }
-    };
+    // Underlying spot, quote and daily bars for every symbol in batched requests
+    let symbols_to_price: Vec<String> = symbol_sides.iter().map(|(symbol, _)| symbol.clone()).collect();
+    let stock_snapshots = alpaca_data::fetch_stock_snapshots(&symbols_to_price, &request_stats).await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to fetch underlying snapshots: {}", e);
+            std::collections::HashMap::new()
+        });
     
     // Create futures for parallel options analysis with better memory management
-    let options_futures: Vec<_> = unique_symbols_vec.iter().map(|symbol| {
+    let options_futures: Vec<_> = symbol_sides.iter().map(|(symbol, sides)| {
+        let request_stats = &request_stats;
         let symbol = symbol.clone();
-        let sentiment = overall_sentiment.to_string();
+        let sides = sides.clone();
+        let mut underlying_metrics = stock_snapshots.get(&symbol)
+            .map(alpaca_data::build_underlying_metrics)
+            .unwrap_or_else(|| serde_json::json!({}));
         async move {
-            match alpaca_data::analyze_ticker_options(&symbol, &serde_json::json!({}), Some(&sentiment)).await {
-                Ok(analysis) => {
-                    let mut top_options = Vec::new();
-                    
-                    // Convert the analysis to our structured format
-                    let options_analysis_vec = if let Some(analysis_array) = analysis["options_analysis"].as_array() {
-                        analysis_array.iter().map(|item| {
-                            let contract = &item["contract"];
-                            
-                            // Calculate financial metrics for the contract
-                            let financial_metrics = alpaca_data::calculate_option_financial_metrics(contract);
-                            
-                            // Create enhanced contract with financial metrics
-                            let mut enhanced_contract = contract.clone();
-                            if let Some(metrics) = financial_metrics {
-                                enhanced_contract["financial_metrics"] = serde_json::json!({
-                                    "sharpe_ratio": metrics.sharpe,
-                                    "sortino_ratio": metrics.sortino,
-                                    "calmar_ratio": metrics.calmar,
-                                    "max_drawdown": metrics.max_drawdown,
-                                    "volatility": metrics.volatility,
-                                    "composite_score": metrics.composite_score,
-                                    "kelly_fraction": metrics.kelly_fraction,
-                                });
-                            }
-                            
-                            OptionAnalysis {
-                                contract_type: item["contract_type"].as_str().unwrap_or("").to_string(),
-                                contract: enhanced_contract,
-                                option_score: item["option_score"].as_f64().unwrap_or(0.0),
-                                undervalued_indicators: item["undervalued_indicators"].as_array()
-                                    .map(|arr| arr.iter().filter_map(|i| i.as_str()).map(|s| s.to_string()).collect())
-                                    .unwrap_or_default(),
-                            }
-                        }).collect()
-                    } else {
-                        Vec::new()
-                    };
-                    
-                    let symbol_analysis = SymbolOptionsAnalysis {
-                        symbol: symbol.clone(),
-                        underlying_metrics: analysis["underlying_metrics"].clone(),
-                        options_analysis: options_analysis_vec,
-                        error: analysis["error"].as_str().map(|s| s.to_string()),
-                    };
-                    
-                    // Collect top options for summary
-                    for option in &symbol_analysis.options_analysis {
-                        if option.option_score > 1.0 {
-                            top_options.push(TopOption {
-                                symbol: symbol.clone(),
-                                score: option.option_score,
-                                indicators: option.undervalued_indicators.clone(),
-                            });
-                        }
-                    }
-                    
-                    Ok::<(SymbolOptionsAnalysis, Vec<TopOption>), String>((symbol_analysis, top_options))
-                }
-                Err(e) => {
-                    let symbol_analysis = SymbolOptionsAnalysis {
-                        symbol: symbol.clone(),
-                        underlying_metrics: serde_json::json!({}),
-                        options_analysis: Vec::new(),
-                        error: Some(e),
-                    };
-                    Ok::<(SymbolOptionsAnalysis, Vec<TopOption>), String>((symbol_analysis, Vec::new()))
-                }
+            let mut options_analysis_vec = Vec::new();
+            let mut errors = Vec::new();
+            
+            // Skew and term structure from the full chain, shared by both sides; an extra chain
+            // fetch per symbol, so only when asked for
+            let spot_price = underlying_metrics.get("spot_price").and_then(|v| v.as_f64());
+            let vol_surface = match spot_price {
+                Some(spot) if params.vol_surface == Some(true) => alpaca_data::build_vol_surface(&symbol, spot, config.clock.as_ref(), request_stats).await
+                    .map_err(|e| tracing::warn!("Volatility surface unavailable for {}: {}", symbol, e))
+                    .ok(),
+                _ => None,
+            };
+            if let Some(summary) = vol_surface.as_ref().and_then(|surface| surface.summary())
+                && let Some(metrics) = underlying_metrics.as_object_mut() {
+                metrics.insert("vol_surface".to_string(), serde_json::to_value(summary).unwrap_or_default());
             }
+            
+            for option_type in sides {
+                let mut analysis = alpaca_data::analyze_ticker_options(&symbol, &underlying_metrics, vol_surface.as_ref(), option_type, config.clock.as_ref(), request_stats).await;
+                attach_financial_metrics(&mut analysis.options_analysis, config.clock.as_ref());
+                options_analysis_vec.extend(analysis.options_analysis);
+                errors.extend(analysis.error);
+            }
+            
+            let symbol_analysis = SymbolOptionsAnalysis {
+                symbol: symbol.clone(),
+                underlying_metrics,
+                options_analysis: options_analysis_vec,
+                error: if errors.is_empty() { None } else { Some(errors.join("; ")) },
+            };
+            
+            // Collect top options for summary
+            let top_options = symbol_analysis.options_analysis.iter()
+                .filter(|option| option.option_score > 1.0)
+                .map(|option| TopOption {
+                    symbol: symbol.clone(),
+                    score: option.option_score,
+                    indicators: option.undervalued_indicators.clone(),
+                })
+                .collect();
+            
+            Ok::<(SymbolOptionsAnalysis, Vec<TopOption>), String>((symbol_analysis, top_options))
         }
     }).collect();
     
     // Execute all futures in parallel with concurrency limit and better memory management
-    let mut options_analysis = Vec::with_capacity(unique_symbols_vec.len());
+    let mut options_analysis = Vec::with_capacity(symbol_sides.len());
     let mut top_options = Vec::new();
     
     // Use futures::stream::iter with buffer_unordered for controlled concurrency
@@ -570,9 +1212,13 @@ async fn perform_analysis(config: &AppConfig) -> anyhow::Result<TradingBotRespon
         }
     }
     
+    // Skipped symbols are still listed, with the reason
+    options_analysis.extend(skipped_symbols);
+    
     // Sort top options by score (highest to lowest)
     top_options.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
-    top_options.truncate(10); // Keep top 10
+    let max_signals = params.max_signals.unwrap_or(DEFAULT_MAX_SIGNALS);
+    top_options.truncate(max_signals);
     
     // Sort options analysis by highest option score for each symbol
     options_analysis.sort_by(|a, b| {
@@ -585,20 +1231,21 @@ async fn perform_analysis(config: &AppConfig) -> anyhow::Result<TradingBotRespon
     let mut trading_signals = Vec::new();
     for symbol_analysis in &options_analysis {
         for option in &symbol_analysis.options_analysis {
-            let sentiment_score = news_analysis.iter()
-                .find(|news| news.symbols.contains(&symbol_analysis.symbol))
-                .map(|news| news.confidence)
-                .unwrap_or(0.5);
+            let sentiment_score = symbol_sentiment.iter()
+                .find(|s| s.symbol == symbol_analysis.symbol)
+                .map(|s| s.score)
+                .unwrap_or(0.0);
             
             let signal = alpaca_data::convert_to_trading_signal(
                 &symbol_analysis.symbol,
                 option,
                 sentiment_score,
-                overall_sentiment,
+                config.clock.as_ref(),
             );
             
-            // Filter out extremely high-risk signals
-            if signal.risk_score < 0.9 && signal.confidence > 0.1 {
+            // Filter out high-risk and low-confidence signals
+            if signal.risk_score < params.max_risk.unwrap_or(DEFAULT_MAX_RISK)
+                && signal.confidence > params.min_confidence.unwrap_or(DEFAULT_MIN_CONFIDENCE) {
                 trading_signals.push(signal);
             }
         }
@@ -606,30 +1253,224 @@ async fn perform_analysis(config: &AppConfig) -> anyhow::Result<TradingBotRespon
     
     // Sort trading signals by confidence (highest to lowest)
     trading_signals.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap_or(std::cmp::Ordering::Equal));
+    trading_signals.truncate(max_signals);
     
     // Calculate market summary and risk metrics
-    let market_summary = alpaca_data::calculate_market_summary(&trading_signals, &news_analysis);
+    let market_summary = alpaca_data::calculate_market_summary(&trading_signals, &news_analysis, config.clock.as_ref());
     let risk_metrics = alpaca_data::calculate_risk_metrics(&trading_signals);
     
     // Create execution metadata
-    // Calculate cache hit rate
-    let total_cache_entries = SENTIMENT_CACHE.len() + OPTIONS_CACHE.len();
-    let cache_hit_rate = if total_cache_entries > 0 { 0.7 } else { 0.0 }; // Estimate based on cache usage
-    
     let execution_metadata = ExecutionMetadata {
         processing_time_ms: 0, // Will be set by the endpoint
         symbols_analyzed: unique_symbols_vec.len(),
         options_analyzed: trading_signals.len(),
         crypto_symbols_filtered: crypto_symbols.len(),
-        api_calls_made: unique_symbols_vec.len() + 1, // +1 for news API
-        cache_hit_rate,
+        api_calls_made: request_stats.api_calls() as usize, // Upstream HTTP requests, retries and pages included
+        cache_hit_rate: request_stats.cache_hit_rate(), // Lookups made by this request only
     };
 
     Ok(TradingBotResponse {
+        parameters: params,
         market_summary,
         trading_signals,
         sentiment_analysis: news_analysis,
+        symbol_sentiment,
         risk_metrics,
         execution_metadata,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{Greeks, OptionRight};
+
+    fn contract(expiration_date: &str, strike_price: f64, implied_volatility: Option<f64>) -> ChainContract {
+        ChainContract {
+            symbol: format!("XYZ{strike_price}"),
+            option_type: OptionRight::Call,
+            strike_price,
+            expiration_date: expiration_date.to_string(),
+            days_to_expiry: 0,
+            bid: None,
+            ask: None,
+            mid: None,
+            spread_pct: None,
+            last_price: None,
+            volume: 0,
+            open_interest: None,
+            implied_volatility,
+            iv_source: None,
+            greeks: implied_volatility.map(|iv| Greeks { delta: iv, ..Default::default() }),
+            greeks_source: None,
+        }
+    }
+
+    fn bad_request(params: AnalyzeParams) -> String {
+        match resolve_analyze_params(params, NeutralSentimentAction::Skip, false) {
+            Err(AppError::BadRequest(message)) => message,
+            other => panic!("expected a 400, got {other:?}"),
+        }
+    }
+
+    fn order(contracts: &[ChainContract]) -> Vec<(&str, f64)> {
+        contracts.iter().map(|c| (c.expiration_date.as_str(), c.strike_price)).collect()
+    }
+
+    #[test]
+    fn analyze_params_fill_in_every_default() {
+        let params = resolve_analyze_params(AnalyzeParams {
+            symbols: Some(vec!["aapl".to_string(), " ".to_string()]),
+            option_type: Some("Both".to_string()),
+            ..Default::default()
+        }, NeutralSentimentAction::Both, false).unwrap();
+        assert_eq!(params.symbols, Some(vec!["AAPL".to_string()]));
+        assert_eq!(params.max_news, Some(DEFAULT_MAX_NEWS));
+        assert_eq!(params.min_confidence, Some(DEFAULT_MIN_CONFIDENCE));
+        assert_eq!(params.max_risk, Some(DEFAULT_MAX_RISK));
+        assert_eq!(params.max_signals, Some(DEFAULT_MAX_SIGNALS));
+        assert_eq!(params.option_type.as_deref(), Some("both"));
+        assert_eq!(params.exclude_contentless, Some(false));
+        assert_eq!(params.neutral_sentiment_action, Some(NeutralSentimentAction::Both));
+        assert_eq!(params.vol_surface, Some(false));
+        assert_eq!(params.lookback_hours, None);
+
+        // An explicit choice wins over the endpoint's default
+        let params = resolve_analyze_params(AnalyzeParams {
+            neutral_sentiment_action: Some(NeutralSentimentAction::Skip),
+            max_signals: Some(3),
+            vol_surface: Some(false),
+            ..Default::default()
+        }, NeutralSentimentAction::Both, true).unwrap();
+        assert_eq!(params.neutral_sentiment_action, Some(NeutralSentimentAction::Skip));
+        assert_eq!(params.max_signals, Some(3));
+        assert_eq!(params.vol_surface, Some(false));
+    }
+
+    #[test]
+    fn analyze_params_reject_bad_values() {
+        let symbols = |list: &[&str]| Some(list.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+        let cases = [
+            (AnalyzeParams { symbols: symbols(&[]), ..Default::default() }, "'symbols'"),
+            (AnalyzeParams { symbols: symbols(&["", " "]), ..Default::default() }, "'symbols'"),
+            (AnalyzeParams { symbols: symbols(&["AAPL$"]), ..Default::default() }, "Invalid symbol"),
+            (AnalyzeParams { symbols: symbols(&["AAPL", "msft"]), exclude_symbols: symbols(&["MSFT"]), ..Default::default() }, "'MSFT'"),
+            (AnalyzeParams { lookback_hours: Some(0.0), ..Default::default() }, "'lookback_hours'"),
+            (AnalyzeParams { lookback_hours: Some(-1.0), ..Default::default() }, "'lookback_hours'"),
+            (AnalyzeParams { lookback_hours: Some(MAX_LOOKBACK_HOURS + 1.0), ..Default::default() }, "'lookback_hours'"),
+            (AnalyzeParams { lookback_hours: Some(f64::NAN), ..Default::default() }, "'lookback_hours'"),
+            (AnalyzeParams { max_news: Some(0), ..Default::default() }, "'max_news'"),
+            (AnalyzeParams { max_news: Some(MAX_NEWS_ITEMS + 1), ..Default::default() }, "'max_news'"),
+            (AnalyzeParams { min_confidence: Some(-0.1), ..Default::default() }, "'min_confidence'"),
+            (AnalyzeParams { min_confidence: Some(1.5), ..Default::default() }, "'min_confidence'"),
+            (AnalyzeParams { max_risk: Some(2.0), ..Default::default() }, "'max_risk'"),
+            (AnalyzeParams { max_signals: Some(0), ..Default::default() }, "'max_signals'"),
+            (AnalyzeParams { option_type: Some("straddle".to_string()), ..Default::default() }, "straddle"),
+        ];
+        for (params, expected) in cases {
+            let described = format!("{params:?}");
+            let message = bad_request(params);
+            assert!(message.contains(expected), "{described}: {message}");
+        }
+
+        // The edges of each range are accepted
+        let edges = AnalyzeParams {
+            lookback_hours: Some(MAX_LOOKBACK_HOURS),
+            max_news: Some(MAX_NEWS_ITEMS),
+            min_confidence: Some(0.0),
+            max_risk: Some(1.0),
+            max_signals: Some(1),
+            ..Default::default()
+        };
+        assert!(resolve_analyze_params(edges, NeutralSentimentAction::Skip, false).is_ok());
+    }
+
+    #[test]
+    fn chain_query_maps_filters() {
+        let query = chain_options_query(&ChainQuery {
+            r#type: Some("PUT".to_string()),
+            strike_price_gte: Some(90.0),
+            strike_price_lte: Some(110.0),
+            expiration_date_gte: Some("2024-01-01".to_string()),
+            page_token: Some("abc".to_string()),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(query.r#type.as_deref(), Some("put"));
+        assert_eq!((query.strike_price_gte, query.strike_price_lte), (Some(90.0), Some(110.0)));
+        assert_eq!(query.page_token.as_deref(), Some("abc"));
+        assert_eq!(query.fetch_all, Some(true));
+        // Only a lower expiry bound leaves the chain open ended, so the default caps apply
+        assert_eq!((query.max_pages, query.max_contracts), (None, None));
+
+        let bounded = chain_options_query(&ChainQuery {
+            expiration_date_lte: Some("2024-03-15".to_string()),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(bounded.max_pages, Some(NARROWED_CHAIN_MAX_PAGES));
+        assert_eq!(bounded.max_contracts, Some(NARROWED_CHAIN_MAX_CONTRACTS));
+    }
+
+    #[test]
+    fn chain_query_rejects_bad_parameters() {
+        let text = |value: &str| Some(value.to_string());
+        let cases = [
+            ChainQuery { r#type: text("straddle"), ..Default::default() },
+            ChainQuery { strike_price_gte: Some(0.0), ..Default::default() },
+            ChainQuery { strike_price_lte: Some(-5.0), ..Default::default() },
+            ChainQuery { strike_price_gte: Some(120.0), strike_price_lte: Some(100.0), ..Default::default() },
+            ChainQuery { expiration_date: text("2024-02-30"), ..Default::default() },
+            ChainQuery { expiration_date_gte: text("03/15/2024"), ..Default::default() },
+            ChainQuery { expiration_date_lte: text("2024-03-15T00:00:00Z"), ..Default::default() },
+            ChainQuery { expiration_date_gte: text("2024-06-21"), expiration_date_lte: text("2024-03-15"), ..Default::default() },
+        ];
+        for params in cases {
+            let result = chain_options_query(&params);
+            assert!(matches!(result, Err(AppError::BadRequest(_))), "{params:?} gave {result:?}");
+        }
+        // A single-day range is not inverted
+        assert!(chain_options_query(&ChainQuery {
+            expiration_date_gte: text("2024-03-15"),
+            expiration_date_lte: text("2024-03-15"),
+            ..Default::default()
+        }).is_ok());
+    }
+
+    #[test]
+    fn sort_chain_puts_missing_values_last_in_either_order() {
+        let mut contracts = vec![
+            contract("2024-02-16", 100.0, None),
+            contract("2024-01-19", 105.0, Some(0.30)),
+            contract("2024-01-19", 95.0, None),
+            contract("2024-01-19", 100.0, Some(0.20)),
+            contract("2024-03-15", 100.0, Some(0.30)),
+        ];
+
+        sort_chain(&mut contracts, ChainSortField::ImpliedVolatility, false);
+        assert_eq!(order(&contracts), [
+            ("2024-01-19", 100.0),
+            ("2024-01-19", 105.0),
+            ("2024-03-15", 100.0),
+            ("2024-01-19", 95.0),
+            ("2024-02-16", 100.0),
+        ]);
+
+        // Descending flips the field but not the tie-breaks, and missing values stay last
+        sort_chain(&mut contracts, ChainSortField::Delta, true);
+        assert_eq!(order(&contracts), [
+            ("2024-01-19", 105.0),
+            ("2024-03-15", 100.0),
+            ("2024-01-19", 100.0),
+            ("2024-01-19", 95.0),
+            ("2024-02-16", 100.0),
+        ]);
+
+        sort_chain(&mut contracts, ChainSortField::Strike, true);
+        assert_eq!(order(&contracts), [
+            ("2024-01-19", 105.0),
+            ("2024-01-19", 100.0),
+            ("2024-02-16", 100.0),
+            ("2024-03-15", 100.0),
+            ("2024-01-19", 95.0),
+        ]);
+    }
+}
@@ -3,29 +3,159 @@ use ort::{
     session::{builder::GraphOptimizationLevel, Session},
     value::Value,
 };
-use tokenizers::Tokenizer;
-use std::sync::Arc;
+use tokenizers::{Encoding, PaddingParams, PaddingStrategy, Tokenizer, TruncationParams};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::path::{Path, PathBuf};
 use std::env;
-use tokio::sync::Mutex;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 #[derive(Debug, Clone)]
 pub struct SentimentResult {
     pub sentiment: String,
     pub confidence: f64,
+    pub probabilities: HashMap<String, f64>,
+    pub net_score: f64,
 }
 
+impl SentimentResult {
+    // Weighted average of several results' class probabilities
+    pub fn weighted_average(parts: &[(f64, &SentimentResult)]) -> Self {
+        let mut labels: Vec<String> = parts.iter()
+            .flat_map(|(_, result)| result.probabilities.keys().cloned())
+            .collect();
+        labels.sort();
+        labels.dedup();
+
+        let weight_sum: f64 = parts.iter().map(|(w, _)| w).sum();
+        if labels.is_empty() || weight_sum <= 0.0 {
+            return Self::neutral();
+        }
+
+        let scores: Vec<f64> = labels.iter()
+            .map(|label| {
+                parts.iter()
+                    .map(|(w, result)| w * result.probabilities.get(label).copied().unwrap_or(0.0))
+                    .sum::<f64>() / weight_sum
+            })
+            .collect();
+
+        Self::from_probabilities(&scores, &labels)
+    }
+
+    // Build a result from class probabilities ordered like `labels`
+    fn from_probabilities(scores: &[f64], labels: &[String]) -> Self {
+        // Get the predicted class and confidence
+        let predicted_class = scores
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map_or(0, |(i, _)| i);
+
+        let confidence = scores.get(predicted_class).copied().unwrap_or(0.0);
+
+        // Map class indices to sentiment labels (based on config.json id2label)
+        let sentiment = labels[predicted_class].clone();
+
+        let probabilities: HashMap<String, f64> = labels.iter()
+            .cloned()
+            .zip(scores.iter().copied())
+            .collect();
+
+        // Signed score: +1 fully positive, -1 fully negative
+        let net_score = probabilities.get("positive").copied().unwrap_or(0.0)
+            - probabilities.get("negative").copied().unwrap_or(0.0);
+
+        Self {
+            sentiment,
+            confidence,
+            probabilities,
+            net_score,
+        }
+    }
+
+    // Placeholder used before a prediction is available
+    pub fn neutral() -> Self {
+        Self {
+            sentiment: "neutral".to_string(),
+            confidence: 0.5,
+            probabilities: HashMap::new(),
+            net_score: 0.0,
+        }
+    }
+}
+
+// How chunk results are combined into a document-level score
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkAggregation {
+    Mean,
+    AttentionWeighted,
+    MaxMagnitude,
+}
+
+impl std::str::FromStr for ChunkAggregation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "mean" => Ok(Self::Mean),
+            "attention_weighted" => Ok(Self::AttentionWeighted),
+            "max_magnitude" => Ok(Self::MaxMagnitude),
+            other => Err(anyhow::anyhow!(
+                "Unknown aggregation '{}', expected one of: mean, attention_weighted, max_magnitude",
+                other
+            )),
+        }
+    }
+}
+
+// One model-sized window of a long document
+#[derive(Debug, Clone)]
+pub struct ChunkSentiment {
+    pub start_offset: usize, // Byte offsets into the trimmed input text
+    pub end_offset: usize,
+    pub token_count: usize,
+    pub result: SentimentResult,
+}
+
+#[derive(Debug, Clone)]
+pub struct LongTextResult {
+    pub document: SentimentResult,
+    pub chunks: Vec<ChunkSentiment>,
+}
+
+// Default number of texts run through the session at once
+pub const DEFAULT_BATCH_SIZE: usize = 8;
+
+// BERT position embeddings only cover 512 tokens
+const MAX_SEQUENCE_LENGTH: usize = 512;
+
+// Tokens shared between consecutive windows in long-text mode
+const WINDOW_STRIDE: usize = 128;
+
+// Input caps in bytes: single-pass texts, and long-text mode where the windows cover the whole text
+const DEFAULT_MAX_TEXT_LENGTH: usize = 10_000;
+const DEFAULT_MAX_LONG_TEXT_LENGTH: usize = 200_000;
+
+// Softmax temperature of attention-weighted aggregation, in units of |net score|
+const ATTENTION_TEMPERATURE: f64 = 0.25;
+
 pub struct OnnxSentimentModel {
     session: Session,
     tokenizer: Tokenizer,
+    labels: Vec<String>,
+    batch_size: usize,
 }
 
 impl OnnxSentimentModel {
-    pub fn new(model_path: &str) -> Result<Self> {
+    pub fn new(model_path: &str, intra_threads: usize) -> Result<Self> {
         let model_dir = Self::resolve_model_path(model_path)?;
         
         let model_file = model_dir.join("model.onnx");
         let tokenizer_file = model_dir.join("tokenizer.json");
+        let config_file = model_dir.join("config.json");
         
         // Verify files exist
         if !model_file.exists() {
@@ -43,6 +173,14 @@ impl OnnxSentimentModel {
                 env::current_dir().unwrap_or_default().display()
             ));
         }
+        
+        if !config_file.exists() {
+            return Err(anyhow::anyhow!(
+                "Model config file not found: {}. Current working directory: {}",
+                config_file.display(),
+                env::current_dir().unwrap_or_default().display()
+            ));
+        }
 
         // Validate model file integrity
         Self::validate_model_file(&model_file)?;
@@ -50,20 +188,110 @@ impl OnnxSentimentModel {
         // Create optimized ONNX Runtime session with error handling
         let session = Session::builder()?
             .with_optimization_level(GraphOptimizationLevel::Level3)? // Use maximum optimization
-            .with_intra_threads(num_cpus::get())? // Use all available cores
+            .with_intra_threads(intra_threads)? // Cores assigned to this session
             .with_inter_threads(2)? // Use 2 inter-threads for better parallelization
             .commit_from_file(&model_file)
             .map_err(|e| anyhow::anyhow!("Failed to load ONNX model: {}. The model may be corrupted or incompatible with this ONNX Runtime version. Try re-downloading the model.", e))?;
 
+        // Load class labels and make sure the model actually produces that many logits
+        let labels = Self::load_labels(&config_file)?;
+        Self::validate_logits_shape(&session, labels.len())?;
+
         // Load tokenizer
-        let tokenizer = Tokenizer::from_file(&tokenizer_file)
+        let mut tokenizer = Tokenizer::from_file(&tokenizer_file)
             .map_err(|e| anyhow::anyhow!("Failed to load tokenizer: {}", e))?;
+        Self::configure_tokenizer(&mut tokenizer)?;
 
         Ok(OnnxSentimentModel {
             session,
             tokenizer,
+            labels,
+            batch_size: DEFAULT_BATCH_SIZE,
         })
     }
+
+    // Read id2label from config.json, ordered by class index
+    fn load_labels(config_file: &Path) -> Result<Vec<String>> {
+        let contents = std::fs::read_to_string(config_file)
+            .map_err(|e| anyhow::anyhow!("Cannot read model config {}: {}", config_file.display(), e))?;
+        let config: serde_json::Value = serde_json::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Invalid model config {}: {}", config_file.display(), e))?;
+
+        let id2label = config.get("id2label")
+            .and_then(|v| v.as_object())
+            .ok_or_else(|| anyhow::anyhow!("Model config {} has no id2label mapping", config_file.display()))?;
+
+        let mut labels = vec![None; id2label.len()];
+        for (id, label) in id2label {
+            let index: usize = id.parse()
+                .map_err(|_| anyhow::anyhow!("Invalid class id '{}' in id2label", id))?;
+            let label = label.as_str()
+                .ok_or_else(|| anyhow::anyhow!("Label for class {} is not a string", id))?;
+            let slot = labels.get_mut(index)
+                .ok_or_else(|| anyhow::anyhow!("Class id {} out of range for {} labels", index, id2label.len()))?;
+            *slot = Some(label.to_lowercase());
+        }
+
+        let labels: Vec<String> = labels.into_iter()
+            .collect::<Option<_>>()
+            .ok_or_else(|| anyhow::anyhow!("id2label class ids are not contiguous from 0"))?;
+
+        // Downstream scoring relies on these two classes being present
+        for required in ["positive", "negative"] {
+            if !labels.iter().any(|l| l == required) {
+                return Err(anyhow::anyhow!("id2label is missing the '{}' class: {:?}", required, labels));
+            }
+        }
+
+        tracing::info!("Model labels: {:?}", labels);
+        Ok(labels)
+    }
+
+    fn validate_logits_shape(session: &Session, num_labels: usize) -> Result<()> {
+        let output = session.outputs.iter()
+            .find(|o| o.name == "logits")
+            .ok_or_else(|| anyhow::anyhow!("ONNX model has no 'logits' output"))?;
+
+        // The class dimension is the last one; -1 means dynamic and is checked at inference time
+        let num_classes = output.output_type.tensor_shape()
+            .and_then(|shape| shape.last().copied())
+            .ok_or_else(|| anyhow::anyhow!("ONNX 'logits' output is not a tensor"))?;
+
+        if num_classes >= 0 && num_classes as usize != num_labels {
+            return Err(anyhow::anyhow!(
+                "Model config declares {} labels but the 'logits' output has {} classes",
+                num_labels,
+                num_classes
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn configure_tokenizer(tokenizer: &mut Tokenizer) -> Result<()> {
+        // Pad every batch to its longest member, keeping the pad token from tokenizer.json if set
+        let padding = tokenizer.get_padding().cloned().unwrap_or_default();
+        tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::BatchLongest,
+            ..padding
+        }));
+
+        // Truncate anything the model cannot see
+        let truncation = tokenizer.get_truncation().cloned().unwrap_or_default();
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: truncation.max_length.min(MAX_SEQUENCE_LENGTH),
+                ..truncation
+            }))
+            .map_err(|e| anyhow::anyhow!("Failed to configure tokenizer truncation: {}", e))?;
+
+        Ok(())
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
     
     fn validate_model_file(model_file: &Path) -> Result<()> {
         use std::fs::File;
@@ -162,42 +390,67 @@ impl OnnxSentimentModel {
     }
 
     pub fn predict(&mut self, text: &str) -> Result<SentimentResult> {
+        Self::validate_text(text, max_text_length())?;
+
+        self.run_batch(&[text.trim()])?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("Model returned no prediction"))
+    }
+
+    pub fn predict_batch(&mut self, texts: &[String]) -> Result<Vec<SentimentResult>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // For single text, use the single prediction path
+        if texts.len() == 1 {
+            return Ok(vec![self.predict(&texts[0])?]);
+        }
+
+        for text in texts {
+            Self::validate_text(text, max_text_length())?;
+        }
+
+        // Run one padded session call per chunk
+        let mut results = Vec::with_capacity(texts.len());
+
+        for chunk in texts.chunks(self.batch_size) {
+            let chunk: Vec<&str> = chunk.iter().map(|text| text.trim()).collect();
+            results.extend(self.run_batch(&chunk)?);
+        }
+
+        Ok(results)
+    }
+
+    fn validate_text(text: &str, max_length: usize) -> Result<()> {
         // Input validation
         if text.trim().is_empty() {
             return Err(anyhow::anyhow!("Input text cannot be empty"));
         }
         
-        let max_length = std::env::var("MAX_TEXT_LENGTH")
-            .ok()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(10000);
-            
         if text.len() > max_length {
             return Err(anyhow::anyhow!("Input text too long (max {} characters)", max_length));
         }
-        
-        // Tokenize the input text
-        let encoding = self.tokenizer.encode(text.trim(), true)
+
+        Ok(())
+    }
+
+    // Tokenize, pad and run a single batch through the session
+    fn run_batch(&mut self, texts: &[&str]) -> Result<Vec<SentimentResult>> {
+        let encodings = self.tokenizer.encode_batch(texts.to_vec(), true)
             .map_err(|e| anyhow::anyhow!("Failed to encode text: {}", e))?;
-        
-        // Prepare input tensors
-        let input_ids = encoding.get_ids();
-        let attention_mask = encoding.get_attention_mask();
-        
+
+        self.run_encodings(&encodings)
+    }
+
+    // Run already padded encodings through the session as one batch
+    fn run_encodings(&mut self, encodings: &[Encoding]) -> Result<Vec<SentimentResult>> {
+        let batch = encodings.len();
+        let (input_ids, attention_mask) = Self::batch_inputs(encodings)?;
+
         // Convert to ONNX tensors
-        let input_ids_tensor = Value::from_array(
-            ndarray::Array2::from_shape_vec(
-                (1, input_ids.len()),
-                input_ids.iter().map(|&x| i64::from(x)).collect(),
-            )?,
-        )?;
-
-        let attention_mask_tensor = Value::from_array(
-            ndarray::Array2::from_shape_vec(
-                (1, attention_mask.len()),
-                attention_mask.iter().map(|&x| i64::from(x)).collect(),
-            )?,
-        )?;
+        let input_ids_tensor = Value::from_array(input_ids)?;
+        let attention_mask_tensor = Value::from_array(attention_mask)?;
 
         // Run inference
         let outputs = self.session.run(ort::inputs![
@@ -205,101 +458,534 @@ impl OnnxSentimentModel {
             "attention_mask" => attention_mask_tensor
         ])?;
 
-        // Extract logits from output
-        let logits_tensor = &outputs["logits"];
-        let logits = logits_tensor.try_extract_tensor::<f32>()?;
-        let (_, logits_data) = logits;
+        // Extract logits from output, shape (batch, num_classes)
+        let (_, logits_data) = outputs["logits"].try_extract_tensor::<f32>()?;
 
-        // Apply softmax to get probabilities 
-        let num_classes = 3; // positive, negative, neutral
-        let mut max_val = f32::NEG_INFINITY;
-        
-        // Find max for numerical stability
-        for &logit in logits_data.iter().take(num_classes) {
-            max_val = max_val.max(logit);
+        let num_classes = self.labels.len();
+        if logits_data.len() != batch * num_classes {
+            return Err(anyhow::anyhow!(
+                "Unexpected logits size {} for batch of {} texts",
+                logits_data.len(),
+                batch
+            ));
         }
-        
-        // Compute softmax
-        let mut sum = 0.0f32;
-        let mut scores = Vec::with_capacity(num_classes);
-        
-        for &logit in logits_data.iter().take(num_classes) {
-            let exp_val = (logit - max_val).exp();
-            scores.push(exp_val);
-            sum += exp_val;
+
+        Ok(logits_data
+            .chunks(num_classes)
+            .map(|logits| Self::logits_to_result(logits, &self.labels))
+            .collect())
+    }
+
+    // Stack padded encodings into (batch, seq_len) input_ids and attention_mask arrays
+    fn batch_inputs(encodings: &[Encoding]) -> Result<(ndarray::Array2<i64>, ndarray::Array2<i64>)> {
+        // Padding is BatchLongest, so every encoding has the same length
+        let batch = encodings.len();
+        let seq_len = encodings.first().map_or(0, |e| e.get_ids().len());
+        if encodings.iter().any(|e| e.get_ids().len() != seq_len) {
+            return Err(anyhow::anyhow!("Encodings in a batch must be padded to the same length"));
         }
-        
+
+        let mut input_ids = Vec::with_capacity(batch * seq_len);
+        let mut attention_mask = Vec::with_capacity(batch * seq_len);
+        for encoding in encodings {
+            input_ids.extend(encoding.get_ids().iter().map(|&x| i64::from(x)));
+            attention_mask.extend(encoding.get_attention_mask().iter().map(|&x| i64::from(x)));
+        }
+
+        Ok((
+            ndarray::Array2::from_shape_vec((batch, seq_len), input_ids)?,
+            ndarray::Array2::from_shape_vec((batch, seq_len), attention_mask)?,
+        ))
+    }
+
+    fn logits_to_result(logits: &[f32], labels: &[String]) -> SentimentResult {
+        // Find max for numerical stability
+        let max_val = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+
+        // Compute softmax
+        let mut scores: Vec<f32> = logits.iter().map(|&logit| (logit - max_val).exp()).collect();
+        let sum: f32 = scores.iter().sum();
+
         // Normalize probabilities
         for score in &mut scores {
             *score /= sum;
         }
 
-        // Get the predicted class and confidence
-        let predicted_class = scores
-            .iter()
-            .enumerate()
-            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-            .map_or(0, |(i, _)| i);
+        let scores: Vec<f64> = scores.into_iter().map(f64::from).collect();
+        SentimentResult::from_probabilities(&scores, labels)
+    }
 
-        let confidence = f64::from(scores[predicted_class]);
+    pub fn predict_long(&mut self, text: &str, aggregation: ChunkAggregation) -> Result<LongTextResult> {
+        Self::validate_text(text, max_long_text_length())?;
 
-        // Map class indices to sentiment labels (based on config.json id2label)
-        let sentiment_labels = ["positive", "negative", "neutral"];
-        let sentiment = sentiment_labels[predicted_class].to_string();
+        let windows = Self::encode_windows(&mut self.tokenizer, text.trim(), WINDOW_STRIDE)?;
 
-        Ok(SentimentResult {
-            sentiment,
-            confidence,
-        })
+        // Score the windows in padded batches
+        let mut results = Vec::with_capacity(windows.len());
+        for batch in windows.chunks(self.batch_size) {
+            results.extend(self.run_encodings(batch)?);
+        }
+
+        let document = aggregate_results(&results, &self.labels, aggregation);
+        let chunks = windows.iter()
+            .zip(results)
+            .map(|(window, result)| Self::window_chunk(window, result))
+            .collect();
+
+        Ok(LongTextResult { document, chunks })
     }
 
-    pub fn predict_batch(&mut self, texts: &[String]) -> Result<Vec<SentimentResult>> {
-        if texts.is_empty() {
-            return Ok(Vec::new());
-        }
+    // Encode with overlapping windows; each overflow is a full model-sized window sharing
+    // `stride` tokens with the previous one
+    fn encode_windows(tokenizer: &mut Tokenizer, text: &str, stride: usize) -> Result<Vec<Encoding>> {
+        let truncation = tokenizer.get_truncation().cloned().unwrap_or_default();
+        tokenizer
+            .with_truncation(Some(TruncationParams { stride, ..truncation.clone() }))
+            .map_err(|e| anyhow::anyhow!("Failed to configure tokenizer truncation: {}", e))?;
+        let encoded = tokenizer.encode(text, true);
+        tokenizer
+            .with_truncation(Some(truncation))
+            .map_err(|e| anyhow::anyhow!("Failed to configure tokenizer truncation: {}", e))?;
 
-        // For single text, use the optimized single prediction
-        if texts.len() == 1 {
-            return Ok(vec![self.predict(&texts[0])?]);
+        let mut encoding = encoded.map_err(|e| anyhow::anyhow!("Failed to encode text: {}", e))?;
+        let mut windows = vec![];
+        let overflowing = encoding.take_overflowing();
+        windows.push(encoding);
+        windows.extend(overflowing);
+
+        Ok(windows)
+    }
+
+    fn window_chunk(window: &Encoding, result: SentimentResult) -> ChunkSentiment {
+        // Byte span of the real (non-special, non-padding) tokens in this window
+        let offsets: Vec<_> = window.get_offsets().iter()
+            .zip(window.get_special_tokens_mask())
+            .zip(window.get_attention_mask())
+            .filter(|((_, special), mask)| **special == 0 && **mask == 1)
+            .map(|((offset, _), _)| *offset)
+            .collect();
+
+        ChunkSentiment {
+            start_offset: offsets.first().map_or(0, |o| o.0),
+            end_offset: offsets.last().map_or(0, |o| o.1),
+            token_count: window.get_attention_mask().iter().filter(|&&m| m == 1).count(),
+            result,
         }
+    }
+}
 
-        // For multiple texts, process in parallel batches
-        let batch_size = 8; // Process 8 texts at a time
-        let mut results = Vec::with_capacity(texts.len());
+// Combine per-window results into one document-level result
+fn aggregate_results(results: &[SentimentResult], labels: &[String], aggregation: ChunkAggregation) -> SentimentResult {
+    let class_probabilities = |result: &SentimentResult| -> Vec<f64> {
+        labels.iter()
+            .map(|label| result.probabilities.get(label).copied().unwrap_or(0.0))
+            .collect()
+    };
 
-        for chunk in texts.chunks(batch_size) {
-            let mut chunk_results = Vec::with_capacity(chunk.len());
-            
-            for text in chunk {
-                let result = self.predict(text)?;
-                chunk_results.push(result);
+    let weighted_mean = |weight: &dyn Fn(&SentimentResult) -> f64| -> Vec<f64> {
+        let mut totals = vec![0.0; labels.len()];
+        let mut weight_sum = 0.0;
+        for result in results {
+            let w = weight(result);
+            for (total, p) in totals.iter_mut().zip(class_probabilities(result)) {
+                *total += w * p;
+            }
+            weight_sum += w;
+        }
+        if weight_sum > 0.0 {
+            for total in &mut totals {
+                *total /= weight_sum;
             }
-            
-            results.extend(chunk_results);
         }
+        totals
+    };
 
-        Ok(results)
+    let probabilities = match aggregation {
+        ChunkAggregation::Mean => weighted_mean(&|_| 1.0),
+        // Soft attention over windows keyed on the classifier's polarity: opinionated windows
+        // dominate and boilerplate counts for little, between mean and max_magnitude
+        ChunkAggregation::AttentionWeighted => {
+            let peak = results.iter().map(|r| r.net_score.abs()).fold(0.0, f64::max);
+            weighted_mean(&|result| ((result.net_score.abs() - peak) / ATTENTION_TEMPERATURE).exp())
+        }
+        // The single most opinionated window decides
+        ChunkAggregation::MaxMagnitude => results.iter()
+            .max_by(|a, b| a.net_score.abs()
+                .partial_cmp(&b.net_score.abs())
+                .unwrap_or(std::cmp::Ordering::Equal))
+            .map(class_probabilities)
+            .unwrap_or_else(|| vec![0.0; labels.len()]),
+    };
+
+    SentimentResult::from_probabilities(&probabilities, labels)
+}
+
+// Longest text accepted by predict and predict_batch (MAX_TEXT_LENGTH)
+fn max_text_length() -> usize {
+    env::var("MAX_TEXT_LENGTH")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_MAX_TEXT_LENGTH)
+}
+
+// Longest text accepted by predict_long (MAX_LONG_TEXT_LENGTH), e.g. a full earnings call transcript
+pub fn max_long_text_length() -> usize {
+    env::var("MAX_LONG_TEXT_LENGTH")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_MAX_LONG_TEXT_LENGTH)
+}
+
+// Pool of independent sessions so concurrent requests can infer in parallel
+pub struct OnnxSentimentPool {
+    models: std::sync::Mutex<Vec<OnnxSentimentModel>>,
+    permits: Arc<Semaphore>,
+    size: usize,
+    in_use: AtomicUsize,
+    total_checkouts: AtomicU64,
+}
+
+// A model checked out of the pool; returned automatically on drop
+pub struct PooledModel {
+    model: Option<OnnxSentimentModel>,
+    pool: Arc<OnnxSentimentPool>,
+    _permit: OwnedSemaphorePermit,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolStats {
+    pub size: usize,
+    pub in_use: usize,
+    pub available: usize,
+    pub utilization: f64,
+    pub total_checkouts: u64,
+}
+
+impl OnnxSentimentPool {
+    pub fn new(models: Vec<OnnxSentimentModel>) -> Self {
+        let size = models.len();
+        Self {
+            models: std::sync::Mutex::new(models),
+            permits: Arc::new(Semaphore::new(size)),
+            size,
+            in_use: AtomicUsize::new(0),
+            total_checkouts: AtomicU64::new(0),
+        }
+    }
+
+    // Wait for a free session and take it out of the pool
+    pub async fn checkout(self: &Arc<Self>) -> Result<PooledModel> {
+        let permit = self.permits.clone().acquire_owned().await
+            .map_err(|_| anyhow::anyhow!("Sentiment model pool is closed"))?;
+
+        // A panic elsewhere while holding the lock leaves the Vec itself intact
+        let model = self.models
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("Sentiment model pool is empty"))?;
+
+        self.in_use.fetch_add(1, Ordering::Relaxed);
+        self.total_checkouts.fetch_add(1, Ordering::Relaxed);
+
+        Ok(PooledModel {
+            model: Some(model),
+            pool: Arc::clone(self),
+            _permit: permit,
+        })
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        let in_use = self.in_use.load(Ordering::Relaxed);
+        PoolStats {
+            size: self.size,
+            in_use,
+            available: self.permits.available_permits(),
+            utilization: if self.size > 0 { in_use as f64 / self.size as f64 } else { 0.0 },
+            total_checkouts: self.total_checkouts.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl std::ops::Deref for PooledModel {
+    type Target = OnnxSentimentModel;
+
+    fn deref(&self) -> &Self::Target {
+        self.model.as_ref().expect("pooled model already returned")
     }
 }
 
-// Thread-safe wrapper for the sentiment model
-pub type OnnxSentimentModelArc = Arc<Mutex<Option<OnnxSentimentModel>>>;
+impl std::ops::DerefMut for PooledModel {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.model.as_mut().expect("pooled model already returned")
+    }
+}
 
-pub async fn initialize_onnx_sentiment_model() -> Result<OnnxSentimentModelArc> {
+impl Drop for PooledModel {
+    fn drop(&mut self) {
+        if let Some(model) = self.model.take() {
+            // Return the session before the permit is released, even if the lock was poisoned,
+            // otherwise the permit outlives its session and later checkouts find the pool empty
+            self.pool.models
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(model);
+            self.pool.in_use.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+pub type OnnxSentimentPoolArc = Arc<OnnxSentimentPool>;
+
+pub async fn initialize_onnx_sentiment_pool(pool_size: usize) -> Result<OnnxSentimentPoolArc> {
     let model_path = std::env::var("SENTIMENT_MODEL_PATH").unwrap_or_else(|_| "finbert-onnx".to_string());
-    let model = OnnxSentimentModel::new(&model_path)?;
-    Ok(Arc::new(Mutex::new(Some(model))))
+    let batch_size = std::env::var("SENTIMENT_BATCH_SIZE")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_BATCH_SIZE);
+
+    // Split the cores between sessions so they don't oversubscribe the CPU
+    let pool_size = pool_size.max(1);
+    let intra_threads = (num_cpus::get() / pool_size).max(1);
+
+    let mut models = Vec::with_capacity(pool_size);
+    for _ in 0..pool_size {
+        let model = OnnxSentimentModel::new(&model_path, intra_threads)?.with_batch_size(batch_size);
+        models.push(model);
+    }
+    tracing::info!(
+        "Sentiment model pool: {} sessions, {} intra threads each, batch size {}",
+        pool_size, intra_threads, batch_size
+    );
+
+    Ok(Arc::new(OnnxSentimentPool::new(models)))
 }
 
 
 pub async fn predict_sentiment_batch(
-    model_arc: &OnnxSentimentModelArc,
+    pool: &OnnxSentimentPoolArc,
     texts: &[String],
 ) -> Result<Vec<SentimentResult>> {
-    let mut model_guard = model_arc.lock().await;
-    let model = model_guard
-        .as_mut()
-        .ok_or_else(|| anyhow::anyhow!("Sentiment model not initialized"))?;
+    let mut model = pool.checkout().await?;
+    let texts = texts.to_vec();
+
+    // ONNX inference is CPU bound, keep it off the async worker threads
+    tokio::task::spawn_blocking(move || model.predict_batch(&texts))
+        .await
+        .map_err(|e| anyhow::anyhow!("Sentiment inference task failed: {}", e))?
+}
+
+pub async fn predict_sentiment_long(
+    pool: &OnnxSentimentPoolArc,
+    text: &str,
+    aggregation: ChunkAggregation,
+) -> Result<LongTextResult> {
+    let mut model = pool.checkout().await?;
+    let text = text.to_string();
+
+    tokio::task::spawn_blocking(move || model.predict_long(&text, aggregation))
+        .await
+        .map_err(|e| anyhow::anyhow!("Sentiment inference task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Needs the exported model (SENTIMENT_MODEL_PATH, default finbert-onnx); run with `cargo test -- --ignored`
+    fn load_model() -> OnnxSentimentModel {
+        let model_path = std::env::var("SENTIMENT_MODEL_PATH").unwrap_or_else(|_| "finbert-onnx".to_string());
+        OnnxSentimentModel::new(&model_path, 1).expect("sentiment model should load")
+    }
+
+    fn assert_same_prediction(single: &SentimentResult, batched: &SentimentResult, text: &str) {
+        assert_eq!(single.sentiment, batched.sentiment, "label differs for {text:?}");
+        for (label, p) in &single.probabilities {
+            let q = batched.probabilities[label];
+            assert!((p - q).abs() < 1e-4, "P({label}) {p} vs {q} for {text:?}");
+        }
+    }
+
+    // Word-level tokenizer over a tiny vocabulary, configured like the real one
+    fn test_tokenizer() -> Tokenizer {
+        let vocab = ["[PAD]", "[UNK]", "revenue", "grew", "losses", "widen", "sharply"]
+            .iter()
+            .enumerate()
+            .map(|(id, token)| (token.to_string(), id as u32))
+            .collect();
+        let model = tokenizers::models::wordlevel::WordLevel::builder()
+            .vocab(vocab)
+            .unk_token("[UNK]".to_string())
+            .build()
+            .unwrap();
+        let mut tokenizer = Tokenizer::new(model);
+        tokenizer.with_pre_tokenizer(Some(tokenizers::pre_tokenizers::whitespace::Whitespace {}));
+        OnnxSentimentModel::configure_tokenizer(&mut tokenizer).unwrap();
+        tokenizer
+    }
 
-    model.predict_batch(texts)
-}
\ No newline at end of file
+    #[test]
+    fn batch_inputs_pad_shorter_texts_and_mask_the_padding() {
+        let tokenizer = test_tokenizer();
+        let encodings = tokenizer
+            .encode_batch(vec!["losses widen sharply", "revenue grew", "profit"], true)
+            .unwrap();
+
+        let (input_ids, attention_mask) = OnnxSentimentModel::batch_inputs(&encodings).unwrap();
+        assert_eq!(input_ids.shape(), &[3, 3]);
+        assert_eq!(input_ids, ndarray::arr2(&[[4, 5, 6], [2, 3, 0], [1, 0, 0]]));
+        assert_eq!(attention_mask, ndarray::arr2(&[[1, 1, 1], [1, 1, 0], [1, 0, 0]]));
+    }
+
+    #[test]
+    fn batch_inputs_reject_unpadded_encodings() {
+        let tokenizer = test_tokenizer();
+        let encodings = vec![
+            tokenizer.encode("losses widen sharply", true).unwrap(),
+            tokenizer.encode("revenue grew", true).unwrap(),
+        ];
+        assert!(OnnxSentimentModel::batch_inputs(&encodings).is_err());
+    }
+
+    fn labels() -> Vec<String> {
+        ["positive", "negative", "neutral"].iter().map(|label| label.to_string()).collect()
+    }
+
+    // A window result with the given positive/negative/neutral probabilities
+    fn window(probabilities: [f64; 3]) -> SentimentResult {
+        SentimentResult::from_probabilities(&probabilities, &labels())
+    }
+
+    #[test]
+    fn mean_aggregation_averages_windows_equally() {
+        let results = [window([0.8, 0.1, 0.1]), window([0.2, 0.6, 0.2])];
+        let document = aggregate_results(&results, &labels(), ChunkAggregation::Mean);
+        assert!((document.probabilities["positive"] - 0.5).abs() < 1e-12);
+        assert!((document.probabilities["negative"] - 0.35).abs() < 1e-12);
+        assert!((document.net_score - 0.15).abs() < 1e-12);
+    }
+
+    #[test]
+    fn max_magnitude_takes_the_most_opinionated_window() {
+        let results = [window([0.5, 0.2, 0.3]), window([0.05, 0.9, 0.05]), window([0.1, 0.1, 0.8])];
+        let document = aggregate_results(&results, &labels(), ChunkAggregation::MaxMagnitude);
+        assert_eq!(document.sentiment, "negative");
+        assert!((document.net_score + 0.85).abs() < 1e-12);
+    }
+
+    #[test]
+    fn attention_weights_are_a_softmax_over_polarity() {
+        // |net| of 0.8 and 0.0: weights 1 and exp(-0.8 / 0.25) relative to the peak
+        let results = [window([0.85, 0.05, 0.1]), window([0.1, 0.1, 0.8])];
+        let document = aggregate_results(&results, &labels(), ChunkAggregation::AttentionWeighted);
+
+        let w = (-0.8 / ATTENTION_TEMPERATURE).exp();
+        let expected = [(0.85 + w * 0.1) / (1.0 + w), (0.05 + w * 0.1) / (1.0 + w), (0.1 + w * 0.8) / (1.0 + w)];
+        for (label, expected) in labels().iter().zip(expected) {
+            assert!((document.probabilities[label] - expected).abs() < 1e-12, "P({label})");
+        }
+    }
+
+    #[test]
+    fn attention_weighting_favours_polar_windows_over_boilerplate() {
+        // One strongly positive window among neutral boilerplate
+        let results = [window([0.9, 0.05, 0.05]), window([0.1, 0.1, 0.8]), window([0.1, 0.1, 0.8])];
+        let mean = aggregate_results(&results, &labels(), ChunkAggregation::Mean);
+        let attention = aggregate_results(&results, &labels(), ChunkAggregation::AttentionWeighted);
+        let max = aggregate_results(&results, &labels(), ChunkAggregation::MaxMagnitude);
+        assert!(mean.net_score < attention.net_score && attention.net_score < max.net_score);
+        assert_eq!(attention.sentiment, "positive");
+        assert_eq!(mean.sentiment, "neutral");
+    }
+
+    #[test]
+    fn attention_weighting_matches_mean_for_equally_polar_windows() {
+        let results = [window([0.7, 0.1, 0.2]), window([0.1, 0.7, 0.2])];
+        let mean = aggregate_results(&results, &labels(), ChunkAggregation::Mean);
+        let attention = aggregate_results(&results, &labels(), ChunkAggregation::AttentionWeighted);
+        for label in labels() {
+            assert!((mean.probabilities[&label] - attention.probabilities[&label]).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn windows_overlap_by_the_stride_and_cover_the_text() {
+        let mut tokenizer = test_tokenizer();
+        let truncation = tokenizer.get_truncation().cloned().unwrap();
+        tokenizer.with_truncation(Some(TruncationParams { max_length: 6, ..truncation })).unwrap();
+
+        let text = "revenue grew losses widen sharply ".repeat(5);
+        let text = text.trim();
+        let windows = OnnxSentimentModel::encode_windows(&mut tokenizer, text, 2).unwrap();
+        let chunks: Vec<ChunkSentiment> = windows.iter()
+            .map(|w| OnnxSentimentModel::window_chunk(w, SentimentResult::neutral()))
+            .collect();
+
+        // 25 words in windows of 6 advancing by 4: 6 windows, the last one short
+        assert_eq!(chunks.len(), 6);
+        assert!(chunks.iter().all(|chunk| chunk.token_count <= 6));
+        assert_eq!(chunks.last().unwrap().token_count, 5);
+        assert_eq!(chunks[0].start_offset, 0);
+        assert_eq!(chunks.last().unwrap().end_offset, text.len());
+        for pair in windows.windows(2) {
+            // The last `stride` tokens of a window open the next one
+            let previous = pair[0].get_ids();
+            assert_eq!(&previous[previous.len() - 2..], &pair[1].get_ids()[..2]);
+        }
+        for pair in chunks.windows(2) {
+            assert!(pair[1].start_offset < pair[0].end_offset);
+        }
+
+        // The tokenizer's own truncation is restored afterwards
+        assert_eq!(tokenizer.get_truncation().unwrap().stride, 0);
+    }
+
+    #[test]
+    #[ignore = "needs the FinBERT ONNX model on disk"]
+    fn long_text_windows_cover_the_document() {
+        let sentence = "Revenue grew twelve percent and the company raised its full-year outlook. ";
+        let text = sentence.repeat(200);
+        let mut model = load_model();
+
+        let long = model.predict_long(&text, ChunkAggregation::Mean).unwrap();
+        assert!(long.chunks.len() > 1);
+        assert!(long.chunks.iter().all(|chunk| chunk.token_count <= MAX_SEQUENCE_LENGTH));
+        assert_eq!(long.chunks[0].start_offset, 0);
+        assert_eq!(long.chunks.last().unwrap().end_offset, text.trim().len());
+        // Consecutive windows overlap
+        for pair in long.chunks.windows(2) {
+            assert!(pair[1].start_offset < pair[0].end_offset);
+        }
+
+        // A text that fits one window scores the same as the single-pass path
+        let short = model.predict_long(sentence, ChunkAggregation::AttentionWeighted).unwrap();
+        assert_eq!(short.chunks.len(), 1);
+        assert_same_prediction(&model.predict(sentence).unwrap(), &short.document, sentence);
+    }
+
+    #[test]
+    #[ignore = "needs the FinBERT ONNX model on disk"]
+    fn padded_batches_match_single_text_inference() {
+        // Very different lengths so most rows are padded, and a partial last batch
+        let texts: Vec<String> = [
+            "Shares jumped after the company beat earnings estimates.",
+            "Losses widen.",
+            "The board approved a quarterly dividend in line with last year, while management reiterated \
+             full-year guidance and said supply chain pressure had eased across every region it operates in.",
+            "Regulators opened an investigation into the bank's lending practices.",
+            "Revenue was flat.",
+        ]
+        .iter()
+        .map(|text| text.to_string())
+        .collect();
+
+        let mut model = load_model().with_batch_size(4);
+        let batched = model.predict_batch(&texts).unwrap();
+        assert_eq!(batched.len(), texts.len());
+
+        for (text, batched) in texts.iter().zip(&batched) {
+            let single = model.predict(text).unwrap();
+            assert_same_prediction(&single, batched, text);
+        }
+    }
+}
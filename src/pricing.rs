@@ -0,0 +1,623 @@
+use crate::types::OptionRight;
+
+const DAYS_PER_YEAR: f64 = 365.0;
+
+// Implied volatility search range and stopping rules
+const IV_MIN: f64 = 1e-4;
+const IV_MAX: f64 = 5.0;
+const IV_PRICE_TOLERANCE: f64 = 1e-8;
+const IV_VOL_TOLERANCE: f64 = 1e-10;
+const IV_MAX_ITERATIONS: u32 = 100;
+
+// Inputs to the Black-Scholes-Merton model; rate, dividend yield and volatility are
+// annualised and continuously compounded, time is in years
+#[derive(Debug, Clone, Copy)]
+pub struct BsmInputs {
+    pub spot: f64,
+    pub strike: f64,
+    pub years: f64,
+    pub rate: f64,
+    pub dividend_yield: f64,
+    pub volatility: f64,
+    pub right: OptionRight,
+}
+
+// Theoretical value and greeks in trading-desk units: theta per calendar day,
+// vega and rho per percentage point of volatility and rate
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OptionValuation {
+    pub price: f64,
+    pub delta: f64,
+    pub gamma: f64,
+    pub theta: f64,
+    pub vega: f64,
+    pub rho: f64,
+}
+
+// European option value and greeks; None when spot, strike, time or volatility is not positive
+pub fn black_scholes_merton(inputs: &BsmInputs) -> Option<OptionValuation> {
+    let BsmInputs { spot, strike, years, rate, dividend_yield: q, volatility: sigma, right } = *inputs;
+    if !(spot > 0.0 && strike > 0.0 && years > 0.0 && sigma > 0.0) {
+        return None;
+    }
+
+    let sqrt_t = years.sqrt();
+    let d1 = ((spot / strike).ln() + (rate - q + 0.5 * sigma * sigma) * years) / (sigma * sqrt_t);
+    let d2 = d1 - sigma * sqrt_t;
+
+    let spot_discount = (-q * years).exp();
+    let strike_discount = (-rate * years).exp();
+    let forward_spot = spot * spot_discount;
+    let pv_strike = strike * strike_discount;
+    let pdf_d1 = norm_pdf(d1);
+
+    // Shared by calls and puts
+    let gamma = spot_discount * pdf_d1 / (spot * sigma * sqrt_t);
+    let vega = forward_spot * pdf_d1 * sqrt_t;
+    let decay = -forward_spot * pdf_d1 * sigma / (2.0 * sqrt_t);
+
+    let (price, delta, theta, rho) = match right {
+        OptionRight::Call => {
+            let (n_d1, n_d2) = (norm_cdf(d1), norm_cdf(d2));
+            (
+                forward_spot * n_d1 - pv_strike * n_d2,
+                spot_discount * n_d1,
+                decay - rate * pv_strike * n_d2 + q * forward_spot * n_d1,
+                pv_strike * years * n_d2,
+            )
+        }
+        OptionRight::Put => {
+            let (n_d1, n_d2) = (norm_cdf(-d1), norm_cdf(-d2));
+            (
+                pv_strike * n_d2 - forward_spot * n_d1,
+                -spot_discount * n_d1,
+                decay + rate * pv_strike * n_d2 - q * forward_spot * n_d1,
+                -pv_strike * years * n_d2,
+            )
+        }
+    };
+
+    Some(OptionValuation {
+        price,
+        delta,
+        gamma,
+        theta: theta / DAYS_PER_YEAR,
+        vega: vega / 100.0,
+        rho: rho / 100.0,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, thiserror::Error)]
+pub enum ImpliedVolError {
+    #[error("spot, strike, time to expiry and option price must be positive")]
+    InvalidInputs,
+    #[error("option price {price:.4} is outside the no-arbitrage range ({lower:.4}, {upper:.4})")]
+    OutsideArbitrageBounds { price: f64, lower: f64, upper: f64 },
+    #[error("option price {price:.4} is not reachable with volatility between {min} and {max}")]
+    OutsideVolatilityRange { price: f64, min: f64, max: f64 },
+    #[error("implied volatility did not converge after {iterations} iterations (last estimate {estimate:.4})")]
+    NotConverged { iterations: u32, estimate: f64 },
+}
+
+// Volatility at which the model reprices the option to `price`; `inputs.volatility` is ignored.
+// Newton steps on vega, falling back to bisection whenever a step leaves the bracket or vega vanishes
+// (deep in or out of the money), so the search never leaves [IV_MIN, IV_MAX].
+pub fn implied_volatility(price: f64, inputs: &BsmInputs) -> Result<f64, ImpliedVolError> {
+    let BsmInputs { spot, strike, years, rate, dividend_yield: q, right, .. } = *inputs;
+    if !(price > 0.0 && spot > 0.0 && strike > 0.0 && years > 0.0) {
+        return Err(ImpliedVolError::InvalidInputs);
+    }
+
+    // European bounds: above the discounted intrinsic value, below the discounted spot (call) or strike (put)
+    let forward_spot = spot * (-q * years).exp();
+    let pv_strike = strike * (-rate * years).exp();
+    let (lower, upper) = match right {
+        OptionRight::Call => ((forward_spot - pv_strike).max(0.0), forward_spot),
+        OptionRight::Put => ((pv_strike - forward_spot).max(0.0), pv_strike),
+    };
+    if price <= lower || price >= upper {
+        return Err(ImpliedVolError::OutsideArbitrageBounds { price, lower, upper });
+    }
+
+    let value_at = |volatility: f64| black_scholes_merton(&BsmInputs { volatility, ..*inputs });
+    let price_at = |volatility: f64| value_at(volatility).map(|v| v.price).unwrap_or(0.0);
+    if price < price_at(IV_MIN) || price > price_at(IV_MAX) {
+        return Err(ImpliedVolError::OutsideVolatilityRange { price, min: IV_MIN, max: IV_MAX });
+    }
+
+    // Brenner-Subrahmanyam at-the-money approximation as the starting point
+    let (mut low, mut high) = (IV_MIN, IV_MAX);
+    let mut sigma = ((2.0 * std::f64::consts::PI / years).sqrt() * price / spot).clamp(0.05, 3.0);
+
+    for _ in 0..IV_MAX_ITERATIONS {
+        let Some(value) = value_at(sigma) else { break };
+        let error = value.price - price;
+        if error.abs() < IV_PRICE_TOLERANCE {
+            return Ok(sigma);
+        }
+        if error > 0.0 { high = sigma } else { low = sigma }
+
+        let vega = value.vega * 100.0; // per unit of volatility
+        let newton = sigma - error / vega;
+        sigma = if vega > f64::EPSILON && newton > low && newton < high {
+            newton
+        } else {
+            0.5 * (low + high)
+        };
+        if high - low < IV_VOL_TOLERANCE {
+            return Ok(sigma);
+        }
+    }
+
+    Err(ImpliedVolError::NotConverged { iterations: IV_MAX_ITERATIONS, estimate: sigma })
+}
+
+// How contracts are valued: closed-form European, or a binomial tree that allows early exercise
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PricingModel {
+    Auto, // Leisen-Reimer where early exercise can pay, Black-Scholes-Merton elsewhere
+    BlackScholesMerton,
+    CoxRossRubinstein,
+    LeisenReimer,
+}
+
+impl PricingModel {
+    // Resolve Auto for one contract: early exercise can only be optimal for puts and for calls
+    // on an underlying that pays a dividend
+    pub fn for_contract(self, inputs: &BsmInputs) -> PricingModel {
+        match self {
+            PricingModel::Auto if inputs.right == OptionRight::Put || inputs.dividend_yield > 0.0 => {
+                PricingModel::LeisenReimer
+            }
+            PricingModel::Auto => PricingModel::BlackScholesMerton,
+            model => model,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PricingModel::Auto => "auto",
+            PricingModel::BlackScholesMerton => "black_scholes_merton",
+            PricingModel::CoxRossRubinstein => "cox_ross_rubinstein",
+            PricingModel::LeisenReimer => "leisen_reimer",
+        }
+    }
+}
+
+impl std::str::FromStr for PricingModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "bsm" | "black_scholes_merton" | "european" => Ok(Self::BlackScholesMerton),
+            "crr" | "cox_ross_rubinstein" => Ok(Self::CoxRossRubinstein),
+            "lr" | "leisen_reimer" => Ok(Self::LeisenReimer),
+            other => Err(format!("Unknown pricing model '{other}', expected auto, bsm, crr or leisen_reimer")),
+        }
+    }
+}
+
+// A contract's valuation under the model chosen for it
+#[derive(Debug, Clone, Copy)]
+pub struct ContractValuation {
+    pub model: PricingModel,
+    pub value: OptionValuation,
+    // American value above the European one; zero when valued as European
+    pub early_exercise_premium: f64,
+}
+
+// Value a contract with `model` (Auto is resolved per contract); trees use `steps` time steps
+pub fn value_option(inputs: &BsmInputs, model: PricingModel, steps: usize) -> Option<ContractValuation> {
+    let european = black_scholes_merton(inputs)?;
+    let model = model.for_contract(inputs);
+    let value = match model {
+        PricingModel::CoxRossRubinstein | PricingModel::LeisenReimer => {
+            finite_difference_greeks(inputs, |bumped| binomial_american(bumped, model, steps))?
+        }
+        _ => european,
+    };
+    Some(ContractValuation {
+        model,
+        value,
+        early_exercise_premium: if model == PricingModel::BlackScholesMerton {
+            0.0
+        } else {
+            (value.price - european.price).max(0.0)
+        },
+    })
+}
+
+// Volatility at which `model` (Auto is resolved per contract) reprices the option to `price`, so a
+// valuation at that volatility with the same model returns the price. The closed form defers to
+// implied_volatility; trees are bisected, their price rising with volatility.
+pub fn model_implied_volatility(price: f64, inputs: &BsmInputs, model: PricingModel, steps: usize) -> Result<f64, ImpliedVolError> {
+    let model = model.for_contract(inputs);
+    if model == PricingModel::BlackScholesMerton {
+        return implied_volatility(price, inputs);
+    }
+
+    let BsmInputs { spot, strike, years, right, .. } = *inputs;
+    if !(price > 0.0 && spot > 0.0 && strike > 0.0 && years > 0.0) {
+        return Err(ImpliedVolError::InvalidInputs);
+    }
+
+    // American bounds: above immediate exercise, below the spot (call) or strike (put)
+    let (lower, upper) = match right {
+        OptionRight::Call => ((spot - strike).max(0.0), spot),
+        OptionRight::Put => ((strike - spot).max(0.0), strike),
+    };
+    if price <= lower || price >= upper {
+        return Err(ImpliedVolError::OutsideArbitrageBounds { price, lower, upper });
+    }
+
+    // A tree with no price at a volatility (CRR with drift swamping it) sits below the target
+    let price_at = |volatility: f64| binomial_american(&BsmInputs { volatility, ..*inputs }, model, steps);
+    let out_of_range = ImpliedVolError::OutsideVolatilityRange { price, min: IV_MIN, max: IV_MAX };
+    if price_at(IV_MAX).is_none_or(|max_price| price > max_price)
+        || price_at(IV_MIN).is_some_and(|min_price| price < min_price) {
+        return Err(out_of_range);
+    }
+
+    let (mut low, mut high) = (IV_MIN, IV_MAX);
+    for _ in 0..IV_MAX_ITERATIONS {
+        let sigma = 0.5 * (low + high);
+        match price_at(sigma) {
+            Some(value) if (value - price).abs() < IV_PRICE_TOLERANCE => return Ok(sigma),
+            Some(value) if value > price => high = sigma,
+            _ => low = sigma,
+        }
+        if high - low < IV_VOL_TOLERANCE {
+            return Ok(0.5 * (low + high));
+        }
+    }
+
+    Err(ImpliedVolError::NotConverged { iterations: IV_MAX_ITERATIONS, estimate: 0.5 * (low + high) })
+}
+
+// American option value on a recombining binomial tree. Leisen-Reimer matches the terminal
+// distribution with Peizer-Pratt inversion and needs an odd step count, so even counts are bumped.
+fn binomial_american(inputs: &BsmInputs, model: PricingModel, steps: usize) -> Option<f64> {
+    let BsmInputs { spot, strike, years, rate, dividend_yield: q, volatility: sigma, right } = *inputs;
+    if !(spot > 0.0 && strike > 0.0 && years > 0.0 && sigma > 0.0) {
+        return None;
+    }
+
+    let steps = match model {
+        PricingModel::LeisenReimer => steps.max(1) | 1,
+        _ => steps.max(1),
+    };
+    let n = steps as f64;
+    let dt = years / n;
+    let growth = ((rate - q) * dt).exp();
+    let discount = (-rate * dt).exp();
+
+    let (up, down, p) = match model {
+        PricingModel::LeisenReimer => {
+            let sqrt_t = years.sqrt();
+            let d1 = ((spot / strike).ln() + (rate - q + 0.5 * sigma * sigma) * years) / (sigma * sqrt_t);
+            let d2 = d1 - sigma * sqrt_t;
+            let p = peizer_pratt(d2, n);
+            let up = growth * peizer_pratt(d1, n) / p;
+            (up, (growth - p * up) / (1.0 - p), p)
+        }
+        _ => {
+            let up = (sigma * dt.sqrt()).exp();
+            let down = 1.0 / up;
+            (up, down, (growth - down) / (up - down))
+        }
+    };
+    // CRR probabilities leave [0, 1] when the drift swamps the volatility over one step
+    if !(p > 0.0 && p < 1.0) {
+        return None;
+    }
+
+    let exercise = |underlying: f64| match right {
+        OptionRight::Call => (underlying - strike).max(0.0),
+        OptionRight::Put => (strike - underlying).max(0.0),
+    };
+    let node_spot = |step: usize, ups: usize| spot * up.powi(ups as i32) * down.powi((step - ups) as i32);
+
+    let mut values: Vec<f64> = (0..=steps).map(|ups| exercise(node_spot(steps, ups))).collect();
+    for step in (0..steps).rev() {
+        for ups in 0..=step {
+            let continuation = discount * (p * values[ups + 1] + (1.0 - p) * values[ups]);
+            values[ups] = continuation.max(exercise(node_spot(step, ups)));
+        }
+    }
+    Some(values[0])
+}
+
+// Peizer-Pratt method 2 inversion of the normal CDF onto a binomial probability
+fn peizer_pratt(z: f64, n: f64) -> f64 {
+    let x = z / (n + 1.0 / 3.0 + 0.1 / (n + 1.0));
+    0.5 + z.signum() * (0.25 - 0.25 * (-x * x * (n + 1.0 / 6.0)).exp()).sqrt()
+}
+
+// Greeks of any pricer by central differences (forward in time for theta), in the same
+// units as the closed form: theta per calendar day, vega and rho per percentage point
+fn finite_difference_greeks(inputs: &BsmInputs, price: impl Fn(&BsmInputs) -> Option<f64>) -> Option<OptionValuation> {
+    let base = price(inputs)?;
+
+    let spot_bump = inputs.spot * 0.01;
+    let spot_up = price(&BsmInputs { spot: inputs.spot + spot_bump, ..*inputs })?;
+    let spot_down = price(&BsmInputs { spot: inputs.spot - spot_bump, ..*inputs })?;
+
+    let vol_bump = (inputs.volatility / 2.0).min(0.01);
+    let vol_up = price(&BsmInputs { volatility: inputs.volatility + vol_bump, ..*inputs })?;
+    let vol_down = price(&BsmInputs { volatility: inputs.volatility - vol_bump, ..*inputs })?;
+
+    let rate_up = price(&BsmInputs { rate: inputs.rate + 0.01, ..*inputs })?;
+    let rate_down = price(&BsmInputs { rate: inputs.rate - 0.01, ..*inputs })?;
+
+    // One day closer to expiry, or half the remaining time when less than a day is left
+    let day = (1.0 / DAYS_PER_YEAR).min(inputs.years / 2.0);
+    let later = price(&BsmInputs { years: inputs.years - day, ..*inputs })?;
+
+    Some(OptionValuation {
+        price: base,
+        delta: (spot_up - spot_down) / (2.0 * spot_bump),
+        gamma: (spot_up - 2.0 * base + spot_down) / (spot_bump * spot_bump),
+        theta: (later - base) / (day * DAYS_PER_YEAR),
+        vega: (vol_up - vol_down) / (2.0 * vol_bump) / 100.0,
+        rho: (rate_up - rate_down) / 2.0,
+    })
+}
+
+// Calendar days to expiry as a year fraction
+pub fn years_from_days(calendar_days: f64) -> f64 {
+    calendar_days / DAYS_PER_YEAR
+}
+
+fn norm_pdf(x: f64) -> f64 {
+    (-0.5 * x * x).exp() / (2.0 * std::f64::consts::PI).sqrt()
+}
+
+// Standard normal CDF to double precision (Hart 1968, as given by West 2005)
+fn norm_cdf(x: f64) -> f64 {
+    let z = x.abs();
+    let tail = if z > 37.0 {
+        0.0
+    } else if z < 7.071_067_811_865_47 {
+        let numerator = ((((((0.035_262_496_599_891_1 * z + 0.700_383_064_443_688) * z
+            + 6.373_962_203_531_65) * z
+            + 33.912_866_078_383) * z
+            + 112.079_291_497_871) * z
+            + 221.213_596_169_931) * z
+            + 220.206_867_912_376)
+            * (-0.5 * z * z).exp();
+        let denominator = ((((((0.088_388_347_648_318_4 * z + 1.755_667_163_182_64) * z
+            + 16.064_177_579_207) * z
+            + 86.780_732_202_946_1) * z
+            + 296.564_248_779_674) * z
+            + 637.333_633_378_831) * z
+            + 793.826_512_519_948) * z
+            + 440.413_735_824_752;
+        numerator / denominator
+    } else {
+        let continued_fraction = z + 1.0 / (z + 2.0 / (z + 3.0 / (z + 4.0 / (z + 0.65))));
+        (-0.5 * z * z).exp() / continued_fraction / 2.506_628_274_631
+    };
+    if x > 0.0 { 1.0 - tail } else { tail }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs(spot: f64, strike: f64, years: f64, rate: f64, q: f64, sigma: f64, right: OptionRight) -> BsmInputs {
+        BsmInputs {
+            spot,
+            strike,
+            years,
+            rate,
+            dividend_yield: q,
+            volatility: sigma,
+            right,
+        }
+    }
+
+    fn value(spot: f64, strike: f64, years: f64, rate: f64, q: f64, sigma: f64, right: OptionRight) -> OptionValuation {
+        black_scholes_merton(&inputs(spot, strike, years, rate, q, sigma, right)).expect("valid inputs")
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn normal_cdf_matches_tables() {
+        assert_close(norm_cdf(0.0), 0.5, 1e-15);
+        assert_close(norm_cdf(1.0), 0.841_344_746_068_543, 1e-12);
+        assert_close(norm_cdf(-1.96), 0.024_997_895_148_220, 1e-12);
+        assert_close(norm_cdf(8.0), 1.0 - 6.220_960_574_271_78e-16, 1e-15);
+    }
+
+    // Hull, Options, Futures and Other Derivatives: S=K=100, r=5%, sigma=20%, T=1
+    #[test]
+    fn at_the_money_reference_values() {
+        let call = value(100.0, 100.0, 1.0, 0.05, 0.0, 0.2, OptionRight::Call);
+        assert_close(call.price, 10.4506, 1e-4);
+        assert_close(call.delta, 0.636_831, 1e-6);
+        assert_close(call.gamma, 0.018_762, 1e-6);
+        assert_close(call.vega, 0.375_240, 1e-6);
+        assert_close(call.theta, -6.414_028 / 365.0, 1e-6);
+        assert_close(call.rho, 0.532_325, 1e-6);
+
+        let put = value(100.0, 100.0, 1.0, 0.05, 0.0, 0.2, OptionRight::Put);
+        assert_close(put.price, 5.5735, 1e-4);
+        assert_close(put.delta, -0.363_169, 1e-6);
+        assert_close(put.gamma, call.gamma, 1e-12);
+        assert_close(put.vega, call.vega, 1e-12);
+        assert_close(put.theta, -1.657_880 / 365.0, 1e-6);
+        assert_close(put.rho, -0.418_904, 1e-6);
+    }
+
+    // Hull example: S=42, K=40, r=10%, sigma=20%, six months
+    #[test]
+    fn hull_stock_option_example() {
+        assert_close(value(42.0, 40.0, 0.5, 0.1, 0.0, 0.2, OptionRight::Call).price, 4.76, 5e-3);
+        assert_close(value(42.0, 40.0, 0.5, 0.1, 0.0, 0.2, OptionRight::Put).price, 0.81, 5e-3);
+    }
+
+    // Haug, The Complete Guide to Option Pricing Formulas: index put with continuous yield
+    #[test]
+    fn dividend_yield_reference_value() {
+        let put = value(100.0, 95.0, 0.5, 0.1, 0.05, 0.2, OptionRight::Put);
+        assert_close(put.price, 2.4648, 1e-4);
+    }
+
+    #[test]
+    fn put_call_parity_with_dividends() {
+        let (spot, strike, years, rate, q) = (930.0, 900.0, 2.0 / 12.0, 0.08, 0.03);
+        let call = value(spot, strike, years, rate, q, 0.2, OptionRight::Call);
+        let put = value(spot, strike, years, rate, q, 0.2, OptionRight::Put);
+        let parity = spot * (-q * years).exp() - strike * (-rate * years).exp();
+        assert_close(call.price - put.price, parity, 1e-9);
+        assert_close(call.delta - put.delta, (-q * years).exp(), 1e-12);
+        // Hull index option example
+        assert_close(call.price, 51.83, 5e-3);
+    }
+
+    fn solve(price: f64, spot: f64, strike: f64, years: f64, rate: f64, q: f64, right: OptionRight) -> Result<f64, ImpliedVolError> {
+        implied_volatility(price, &inputs(spot, strike, years, rate, q, 0.0, right))
+    }
+
+    #[test]
+    fn implied_volatility_recovers_reference_prices() {
+        assert_close(solve(10.4506, 100.0, 100.0, 1.0, 0.05, 0.0, OptionRight::Call).unwrap(), 0.2, 1e-5);
+        assert_close(solve(5.5735, 100.0, 100.0, 1.0, 0.05, 0.0, OptionRight::Put).unwrap(), 0.2, 1e-5);
+        assert_close(solve(2.4648, 100.0, 95.0, 0.5, 0.1, 0.05, OptionRight::Put).unwrap(), 0.2, 1e-4);
+    }
+
+    #[test]
+    fn implied_volatility_round_trips_across_moneyness() {
+        for (strike, sigma, right) in [
+            (40.0, 0.35, OptionRight::Call),  // deep in the money
+            (250.0, 0.6, OptionRight::Call),  // deep out of the money
+            (160.0, 0.25, OptionRight::Put),  // deep in the money
+            (70.0, 1.5, OptionRight::Put),    // out of the money, very high vol
+            (100.0, 0.02, OptionRight::Call), // very low vol
+        ] {
+            let price = value(100.0, strike, 0.75, 0.04, 0.01, sigma, right).price;
+            let solved = solve(price, 100.0, strike, 0.75, 0.04, 0.01, right).unwrap();
+            assert_close(solved, sigma, 1e-4);
+        }
+    }
+
+    #[test]
+    fn implied_volatility_rejects_unreachable_prices() {
+        // Below intrinsic value
+        assert!(matches!(
+            solve(4.0, 110.0, 100.0, 0.25, 0.0, 0.0, OptionRight::Call),
+            Err(ImpliedVolError::OutsideArbitrageBounds { .. })
+        ));
+        // Above the underlying itself
+        assert!(matches!(
+            solve(120.0, 100.0, 100.0, 0.25, 0.0, 0.0, OptionRight::Call),
+            Err(ImpliedVolError::OutsideArbitrageBounds { .. })
+        ));
+        assert_eq!(
+            solve(0.0, 100.0, 100.0, 0.25, 0.0, 0.0, OptionRight::Put),
+            Err(ImpliedVolError::InvalidInputs)
+        );
+    }
+
+    fn american(contract: BsmInputs, model: PricingModel, steps: usize) -> ContractValuation {
+        value_option(&contract, model, steps).expect("valid inputs")
+    }
+
+    // Hull, American put example: S=K=50, r=10%, sigma=40%, five months
+    #[test]
+    fn binomial_american_put_reference_values() {
+        let five_step = american(inputs(50.0, 50.0, 5.0 / 12.0, 0.1, 0.0, 0.4, OptionRight::Put), PricingModel::CoxRossRubinstein, 5);
+        assert_close(five_step.value.price, 4.49, 5e-3);
+
+        let converged = american(inputs(50.0, 50.0, 5.0 / 12.0, 0.1, 0.0, 0.4, OptionRight::Put), PricingModel::LeisenReimer, 201);
+        assert_close(converged.value.price, 4.284, 3e-3);
+        assert!(converged.early_exercise_premium > 0.1);
+
+        let crr = american(inputs(50.0, 50.0, 5.0 / 12.0, 0.1, 0.0, 0.4, OptionRight::Put), PricingModel::CoxRossRubinstein, 500);
+        assert_close(crr.value.price, converged.value.price, 5e-3);
+    }
+
+    // Without dividends an American call is never exercised early, so the tree must agree with the closed form
+    #[test]
+    fn binomial_call_without_dividends_matches_closed_form() {
+        let closed_form = value(100.0, 100.0, 1.0, 0.05, 0.0, 0.2, OptionRight::Call);
+        let tree = american(inputs(100.0, 100.0, 1.0, 0.05, 0.0, 0.2, OptionRight::Call), PricingModel::LeisenReimer, 201);
+        assert_close(tree.value.price, closed_form.price, 1e-3);
+        assert_close(tree.value.delta, closed_form.delta, 1e-3);
+        assert_close(tree.value.gamma, closed_form.gamma, 1e-3);
+        assert_close(tree.value.vega, closed_form.vega, 1e-3);
+        assert_close(tree.value.theta, closed_form.theta, 1e-3);
+        assert_close(tree.value.rho, closed_form.rho, 1e-3);
+        assert!(tree.early_exercise_premium < 1e-3);
+    }
+
+    #[test]
+    fn auto_model_uses_trees_only_where_early_exercise_can_pay() {
+        let call = american(inputs(100.0, 100.0, 1.0, 0.05, 0.0, 0.2, OptionRight::Call), PricingModel::Auto, 101);
+        assert_eq!(call.model, PricingModel::BlackScholesMerton);
+        assert_eq!(call.early_exercise_premium, 0.0);
+
+        let dividend_call = american(inputs(100.0, 90.0, 1.0, 0.03, 0.06, 0.2, OptionRight::Call), PricingModel::Auto, 101);
+        assert_eq!(dividend_call.model, PricingModel::LeisenReimer);
+        assert!(dividend_call.early_exercise_premium > 0.0);
+
+        let put = american(inputs(100.0, 110.0, 1.0, 0.05, 0.0, 0.2, OptionRight::Put), PricingModel::Auto, 101);
+        assert_eq!(put.model, PricingModel::LeisenReimer);
+        assert!(put.value.price >= 10.0); // never below immediate exercise
+    }
+
+    #[test]
+    fn tree_implied_volatility_round_trips_american_prices() {
+        let put = inputs(50.0, 55.0, 5.0 / 12.0, 0.1, 0.0, 0.35, OptionRight::Put);
+        let american_price = american(put, PricingModel::LeisenReimer, 201).value.price;
+
+        let tree_iv = model_implied_volatility(american_price, &put, PricingModel::Auto, 201).unwrap();
+        assert_close(tree_iv, 0.35, 1e-6);
+
+        // The closed form reads the early-exercise premium as extra volatility
+        let european_iv = implied_volatility(american_price, &put).unwrap();
+        assert!(european_iv > 0.36);
+        let double_counted = american(BsmInputs { volatility: european_iv, ..put }, PricingModel::Auto, 201);
+        assert!(double_counted.value.price > american_price + 0.1);
+    }
+
+    #[test]
+    fn model_implied_volatility_uses_the_closed_form_for_european_contracts() {
+        let call = inputs(100.0, 105.0, 0.5, 0.05, 0.0, 0.25, OptionRight::Call);
+        let price = value(100.0, 105.0, 0.5, 0.05, 0.0, 0.25, OptionRight::Call).price;
+        assert_eq!(
+            model_implied_volatility(price, &call, PricingModel::Auto, 201),
+            implied_volatility(price, &call)
+        );
+
+        // Below immediate exercise is unreachable on a tree
+        let put = inputs(50.0, 60.0, 0.5, 0.05, 0.0, 0.3, OptionRight::Put);
+        assert!(matches!(
+            model_implied_volatility(9.5, &put, PricingModel::LeisenReimer, 201),
+            Err(ImpliedVolError::OutsideArbitrageBounds { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_degenerate_inputs() {
+        let inputs = BsmInputs {
+            spot: 100.0,
+            strike: 100.0,
+            years: 0.0,
+            rate: 0.05,
+            dividend_yield: 0.0,
+            volatility: 0.2,
+            right: OptionRight::Call,
+        };
+        assert!(black_scholes_merton(&inputs).is_none());
+        assert!(black_scholes_merton(&BsmInputs { years: 1.0, volatility: 0.0, ..inputs }).is_none());
+        assert!(black_scholes_merton(&BsmInputs { years: 1.0, strike: -1.0, ..inputs }).is_none());
+    }
+}
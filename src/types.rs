@@ -12,6 +12,410 @@ pub struct OptionsQuery {
     pub expiration_date_lte: Option<String>,
     pub root_symbol: Option<String>,
     pub page_token: Option<String>,
+    pub fetch_all: Option<bool>, // Follow next_page_token through the whole chain
+    pub max_pages: Option<u32>,
+    pub max_contracts: Option<usize>,
+}
+
+// Call or put side of an option contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OptionRight {
+    Call,
+    Put,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum OccSymbolError {
+    #[error("contract symbol '{0}' is too short for the OCC format")]
+    TooShort(String),
+    #[error("invalid option root in '{0}'")]
+    InvalidRoot(String),
+    #[error("invalid expiration date in '{0}'")]
+    InvalidExpiration(String),
+    #[error("invalid call/put flag in '{0}'")]
+    InvalidRight(String),
+    #[error("invalid strike price in '{0}'")]
+    InvalidStrike(String),
+}
+
+// OCC option symbol: root (1-6 chars, space padded in the 21-char form),
+// expiration YYMMDD, C/P, and the strike in thousandths as 8 digits.
+// Alpaca contract keys are the same layout without the root padding,
+// e.g. "AAPL240119C00150000" or the adjusted root "BRKB1250620P00400000".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OccSymbol {
+    pub root: String,
+    pub expiration: chrono::NaiveDate,
+    pub right: OptionRight,
+    pub strike_thousandths: u64,
+}
+
+impl OccSymbol {
+    pub fn strike(&self) -> f64 {
+        self.strike_thousandths as f64 / 1000.0
+    }
+}
+
+impl std::str::FromStr for OccSymbol {
+    type Err = OccSymbolError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Parse from the right: the date, flag and strike are fixed width, the root is not
+        if !s.is_ascii() || s.len() < 16 {
+            return Err(OccSymbolError::TooShort(s.to_string()));
+        }
+        let (head, strike) = s.split_at(s.len() - 8);
+        let (head, right) = head.split_at(head.len() - 1);
+        let (root, date) = head.split_at(head.len() - 6);
+
+        let root = root.trim_end();
+        if root.is_empty() || root.len() > 6 || !root.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(OccSymbolError::InvalidRoot(s.to_string()));
+        }
+
+        let expiration = if date.chars().all(|c| c.is_ascii_digit()) {
+            chrono::NaiveDate::parse_from_str(&format!("20{date}"), "%Y%m%d").ok()
+        } else {
+            None
+        }
+        .ok_or_else(|| OccSymbolError::InvalidExpiration(s.to_string()))?;
+
+        let right = match right {
+            "C" => OptionRight::Call,
+            "P" => OptionRight::Put,
+            _ => return Err(OccSymbolError::InvalidRight(s.to_string())),
+        };
+
+        if !strike.chars().all(|c| c.is_ascii_digit()) {
+            return Err(OccSymbolError::InvalidStrike(s.to_string()));
+        }
+        let strike_thousandths = strike.parse()
+            .map_err(|_| OccSymbolError::InvalidStrike(s.to_string()))?;
+
+        Ok(Self {
+            root: root.to_string(),
+            expiration,
+            right,
+            strike_thousandths,
+        })
+    }
+}
+
+impl std::fmt::Display for OccSymbol {
+    // Unpadded form, matching Alpaca contract keys
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let right = match self.right {
+            OptionRight::Call => 'C',
+            OptionRight::Put => 'P',
+        };
+        write!(f, "{}{}{}{:08}", self.root, self.expiration.format("%y%m%d"), right, self.strike_thousandths)
+    }
+}
+
+// Alpaca market data models. Only the fields we use are declared; unknown
+// fields are ignored and missing ones fall back to their defaults.
+
+// Explicit nulls fall back to the default too; #[serde(default)] only covers missing fields
+fn deserialize_null_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+// Latest quote for an option or stock (ap/as = ask price/size, bp/bs = bid price/size)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Quote {
+    #[serde(rename = "ap")]
+    pub ask_price: f64,
+    #[serde(rename = "as")]
+    pub ask_size: u64,
+    #[serde(rename = "ax", deserialize_with = "deserialize_null_default")]
+    pub ask_exchange: String,
+    #[serde(rename = "bp")]
+    pub bid_price: f64,
+    #[serde(rename = "bs")]
+    pub bid_size: u64,
+    #[serde(rename = "bx", deserialize_with = "deserialize_null_default")]
+    pub bid_exchange: String,
+    #[serde(rename = "t")]
+    pub timestamp: Option<String>,
+}
+
+impl Quote {
+    // Mid of a two-sided quote; a zero bid is treated as a bid of nothing, so the mid is half the ask
+    pub fn mid(&self) -> Option<f64> {
+        match (self.bid_price > 0.0, self.ask_price > 0.0) {
+            (true, true) => Some((self.bid_price + self.ask_price) / 2.0),
+            (false, true) => Some(self.ask_price / 2.0),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Trade {
+    #[serde(rename = "p")]
+    pub price: f64,
+    #[serde(rename = "s")]
+    pub size: u64,
+    #[serde(rename = "x", deserialize_with = "deserialize_null_default")]
+    pub exchange: String,
+    #[serde(rename = "t")]
+    pub timestamp: Option<String>,
+}
+
+// OHLCV bar (minute, daily or previous daily)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bar {
+    #[serde(rename = "o")]
+    pub open: f64,
+    #[serde(rename = "h")]
+    pub high: f64,
+    #[serde(rename = "l")]
+    pub low: f64,
+    #[serde(rename = "c")]
+    pub close: f64,
+    #[serde(rename = "v")]
+    pub volume: f64,
+    #[serde(rename = "n")]
+    pub trade_count: u64,
+    #[serde(rename = "vw")]
+    pub vwap: f64,
+    #[serde(rename = "t")]
+    pub timestamp: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Greeks {
+    pub delta: f64,
+    pub gamma: f64,
+    pub theta: f64,
+    pub vega: f64,
+    pub rho: f64,
+}
+
+// One entry of the option chain snapshots endpoint
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct OptionSnapshot {
+    pub latest_quote: Option<Quote>,
+    pub latest_trade: Option<Trade>,
+    pub minute_bar: Option<Bar>,
+    pub daily_bar: Option<Bar>,
+    pub prev_daily_bar: Option<Bar>,
+    pub greeks: Option<Greeks>,
+    pub implied_volatility: Option<f64>,
+}
+
+// Underlying stock snapshot from the multi-symbol stock snapshots endpoint
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct StockSnapshot {
+    pub latest_trade: Option<Trade>,
+    pub latest_quote: Option<Quote>,
+    pub minute_bar: Option<Bar>,
+    pub daily_bar: Option<Bar>,
+    pub prev_daily_bar: Option<Bar>,
+}
+
+impl StockSnapshot {
+    // Best available spot: last trade, then quote mid, then today's and yesterday's close
+    pub fn spot_price(&self) -> Option<f64> {
+        let positive = |p: f64| (p > 0.0).then_some(p);
+        self.latest_trade.as_ref().and_then(|t| positive(t.price))
+            .or_else(|| self.latest_quote.as_ref().filter(|q| q.bid_price > 0.0).and_then(Quote::mid))
+            .or_else(|| self.daily_bar.as_ref().and_then(|b| positive(b.close)))
+            .or_else(|| self.prev_daily_bar.as_ref().and_then(|b| positive(b.close)))
+    }
+}
+
+// Option chain snapshots keyed by contract symbol, merged across pages
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OptionChain {
+    pub snapshots: std::collections::BTreeMap<String, OptionSnapshot>,
+    pub next_page_token: Option<String>, // Where to resume when the chain was cut short
+    pub pages_fetched: u32,
+    pub truncated: bool, // More contracts match the query than were fetched (page or contract cap)
+}
+
+// Contract reference data from the options contracts endpoint
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OptionContractInfo {
+    pub symbol: String,
+    pub expiration_date: Option<String>,
+    #[serde(deserialize_with = "deserialize_open_interest")]
+    pub open_interest: Option<u64>,
+    pub open_interest_date: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OptionContractsPage {
+    pub option_contracts: Vec<OptionContractInfo>,
+    pub next_page_token: Option<String>,
+}
+
+// Alpaca returns open interest as a string ("1234"), or null when not yet published
+fn deserialize_open_interest<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(value.and_then(|v| match v {
+        serde_json::Value::String(s) => s.parse().ok(),
+        serde_json::Value::Number(n) => n.as_u64(),
+        _ => None,
+    }))
+}
+
+// A selected contract: its snapshot plus the reference data we attach to it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OptionContract {
+    pub contract_key: String,
+    #[serde(flatten)]
+    pub snapshot: OptionSnapshot,
+    pub open_interest: Option<u64>,
+    pub expiration_date: Option<String>,
+    pub days_to_expiry: Option<i64>,
+    pub trading_days_to_expiry: Option<i64>,
+    pub underlying_price: Option<f64>,
+    pub financial_metrics: Option<ContractFinancialMetrics>,
+}
+
+impl OptionContract {
+    pub fn ask_price(&self) -> f64 {
+        self.snapshot.latest_quote.as_ref().map(|q| q.ask_price).unwrap_or(0.0)
+    }
+
+    // Today's traded contracts from the daily bar, 0 before the first trade
+    pub fn volume(&self) -> u64 {
+        self.snapshot.daily_bar.as_ref().map(|b| b.volume.max(0.0) as u64).unwrap_or(0)
+    }
+
+    pub fn mid_price(&self) -> Option<f64> {
+        self.snapshot.latest_quote.as_ref().and_then(Quote::mid)
+    }
+
+    pub fn occ(&self) -> Option<OccSymbol> {
+        self.contract_key.parse().ok()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContractFinancialMetrics {
+    pub sharpe_ratio: f64,
+    pub sortino_ratio: f64,
+    pub calmar_ratio: f64,
+    pub max_drawdown: f64,
+    pub volatility: f64,
+    pub composite_score: f64,
+    pub kelly_fraction: f64,
+}
+
+// News item from the Alpaca news endpoint
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NewsItem {
+    #[serde(deserialize_with = "deserialize_null_default")]
+    pub id: u64,
+    #[serde(deserialize_with = "deserialize_null_default")]
+    pub headline: String,
+    #[serde(deserialize_with = "deserialize_null_default")]
+    pub summary: String,
+    #[serde(deserialize_with = "deserialize_null_default")]
+    pub content: String,
+    #[serde(deserialize_with = "deserialize_null_default")]
+    pub author: String,
+    #[serde(deserialize_with = "deserialize_null_default")]
+    pub source: String,
+    pub url: Option<String>,
+    #[serde(deserialize_with = "deserialize_null_default")]
+    pub symbols: Vec<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+// Alpaca news request; pages are followed until `limit` items are collected. Each distinct
+// query is cached on its own.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NewsQuery {
+    pub symbols: Vec<String>,  // Empty for market-wide news
+    pub start: Option<String>, // RFC 3339, inclusive
+    pub end: Option<String>,
+    pub limit: usize,
+    pub include_content: bool,
+    pub exclude_contentless: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NewsPage {
+    #[serde(deserialize_with = "deserialize_null_default")]
+    pub news: Vec<NewsItem>,
+    pub next_page_token: Option<String>,
+}
+
+// Which parts of a news item are scored for sentiment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NewsTextSource {
+    Headline,
+    Summary,
+    Body,
+    Combined, // Weighted blend of every non-empty field
+}
+
+impl std::str::FromStr for NewsTextSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "headline" => Ok(Self::Headline),
+            "summary" => Ok(Self::Summary),
+            "body" | "content" => Ok(Self::Body),
+            "combined" => Ok(Self::Combined),
+            other => Err(format!("Unknown news text source '{other}', expected headline, summary, body or combined")),
+        }
+    }
+}
+
+// What to do with symbols whose sentiment falls inside the neutral band
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NeutralSentimentAction {
+    Skip,
+    Both, // Request calls and puts
+}
+
+impl std::str::FromStr for NeutralSentimentAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(Self::Skip),
+            "both" => Ok(Self::Both),
+            other => Err(format!("Unknown neutral sentiment action '{other}', expected skip or both")),
+        }
+    }
+}
+
+// Sentiment of one field of a news item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldSentiment {
+    pub field: NewsTextSource,
+    pub sentiment: String,
+    pub confidence: f64,
+    pub net_score: f64,
+    pub weight: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,12 +424,156 @@ pub struct SentimentAnalysis {
     pub symbols: Vec<String>,
     pub sentiment: String,
     pub confidence: f64,
+    pub probabilities: std::collections::HashMap<String, f64>, // Per-class probabilities
+    pub net_score: f64, // P(positive) - P(negative), in [-1, 1]
+    pub field_results: Vec<FieldSentiment>, // Fields that were scored and their individual results
+    pub created_at: Option<String>, // RFC 3339 publish time from Alpaca
+}
+
+// News sentiment aggregated over every article mentioning a symbol
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolSentiment {
+    pub symbol: String,
+    pub score: f64, // Confidence and recency weighted mean net sentiment, in [-1, 1]
+    pub article_count: usize,
+    pub dispersion: f64, // Weighted standard deviation of the articles' net sentiment
+    pub latest_timestamp: Option<String>,
+    pub direction: String, // "call", "put" or "neutral"
+}
+
+// POST /sentiment request and response
+#[derive(Debug, Clone, Deserialize)]
+pub struct SentimentRequest {
+    pub texts: Vec<SentimentInput>,
+    // Long-text mode: "mean", "attention_weighted" or "max_magnitude"
+    pub aggregation: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SentimentInput {
+    pub id: Option<String>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TextSentiment {
+    pub id: Option<String>,
+    pub sentiment: String,
+    pub confidence: f64,
+    pub probabilities: std::collections::HashMap<String, f64>,
+    pub net_score: f64,
+    pub cached: bool,
+    pub latency_ms: u64, // Inference time of the batch this text was scored in
+    pub chunks: Option<Vec<TextChunkSentiment>>, // Only set in long-text mode
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TextChunkSentiment {
+    pub start_offset: usize,
+    pub end_offset: usize,
+    pub token_count: usize,
+    pub sentiment: String,
+    pub confidence: f64,
+    pub net_score: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SentimentResponse {
+    pub results: Vec<TextSentiment>,
+    pub processing_time_ms: u64,
+}
+
+// GET /options/:symbol/chain query parameters: Alpaca filters, then server-side filtering and sorting
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ChainQuery {
+    pub r#type: Option<String>, // "call" or "put"
+    pub strike_price_gte: Option<f64>,
+    pub strike_price_lte: Option<f64>,
+    pub expiration_date: Option<String>,
+    pub expiration_date_gte: Option<String>,
+    pub expiration_date_lte: Option<String>,
+    pub feed: Option<String>,
+    pub min_volume: Option<u64>,
+    pub min_open_interest: Option<u64>,
+    pub max_spread_pct: Option<f64>,
+    pub sort: Option<String>,  // Any ChainSortField, default expiration
+    pub order: Option<String>, // "asc" (default) or "desc"
+    pub limit: Option<usize>,
+    pub page_token: Option<String>, // next_page_token of a truncated response, to fetch the rest
+}
+
+// Field the chain endpoint sorts by; ties fall back to expiration then strike
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainSortField {
+    Expiration,
+    Strike,
+    Volume,
+    OpenInterest,
+    ImpliedVolatility,
+    SpreadPct,
+    Delta,
+    Mid,
+}
+
+impl std::str::FromStr for ChainSortField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "expiration" | "expiration_date" => Ok(Self::Expiration),
+            "strike" | "strike_price" => Ok(Self::Strike),
+            "volume" => Ok(Self::Volume),
+            "open_interest" => Ok(Self::OpenInterest),
+            "implied_volatility" | "iv" => Ok(Self::ImpliedVolatility),
+            "spread_pct" => Ok(Self::SpreadPct),
+            "delta" => Ok(Self::Delta),
+            "mid" => Ok(Self::Mid),
+            other => Err(format!(
+                "Unknown sort field '{other}', expected expiration, strike, volume, open_interest, implied_volatility, spread_pct, delta or mid"
+            )),
+        }
+    }
+}
+
+// One contract of a normalized option chain
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainContract {
+    pub symbol: String,
+    pub option_type: OptionRight,
+    pub strike_price: f64,
+    pub expiration_date: String,
+    pub days_to_expiry: i64,
+    pub bid: Option<f64>,
+    pub ask: Option<f64>,
+    pub mid: Option<f64>,
+    pub spread_pct: Option<f64>, // (ask - bid) / mid * 100
+    pub last_price: Option<f64>,
+    pub volume: u64,             // Today's contract volume
+    pub open_interest: Option<u64>,
+    pub implied_volatility: Option<f64>,
+    pub iv_source: Option<String>, // "vendor" or "solved"
+    pub greeks: Option<Greeks>,
+    pub greeks_source: Option<String>, // "vendor" or "model"
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OptionChainResponse {
+    pub symbol: String,
+    pub underlying_price: Option<f64>,
+    pub pages_fetched: u32,
+    pub total_contracts: usize, // Fetched contracts, before server-side filters and limit
+    pub returned_contracts: usize,
+    pub truncated: bool, // The pagination caps stopped the fetch; total_contracts is not the whole chain
+    pub next_page_token: Option<String>, // Pass back as page_token to continue a truncated fetch
+    pub contracts: Vec<ChainContract>,
+    pub timestamp: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptionAnalysis {
     pub contract_type: String,
-    pub contract: serde_json::Value,
+    pub option_type: String, // "call" or "put"
+    pub contract: OptionContract,
     pub option_score: f64,
     pub undervalued_indicators: Vec<String>,
 }
@@ -44,21 +592,24 @@ pub struct TradingSignal {
     pub symbol: String,
     pub signal_type: String, // "BUY_CALL", "BUY_PUT", "SELL_CALL", "SELL_PUT"
     pub confidence: f64,
-    pub sentiment_score: f64,
+    pub sentiment_score: f64, // Net sentiment, P(positive) - P(negative)
     pub risk_score: f64,
-    pub expected_return: f64,
+    pub expected_return: Option<f64>, // None without an underlying price and implied volatility
     pub max_loss: f64,
     pub time_horizon: String, // "SHORT_TERM", "LEAP"
     pub entry_price: f64,
     pub strike_price: f64,
     pub expiration_date: String,
-    pub volume: u64,
-    pub open_interest: u64,
-    pub implied_volatility: f64,
-    pub delta: f64,
-    pub gamma: f64,
-    pub theta: f64,
-    pub vega: f64,
+    pub volume: u64,                // Today's contract volume
+    pub open_interest: Option<u64>, // None when Alpaca does not report it
+    pub implied_volatility: Option<f64>, // Implied under pricing_model; None when Alpaca has none and it could not be solved
+    pub iv_source: Option<String>,       // "vendor" (Alpaca snapshot) or "solved" (from the quote mid)
+    pub iv_solver_error: Option<String>, // Why solving from the quote mid failed, if it was attempted
+    pub greeks: Option<Greeks>,
+    pub greeks_source: Option<String>, // "vendor" (Alpaca snapshot) or "model"
+    pub model_price: Option<f64>, // Model value at implied_volatility; None when it is unknown
+    pub pricing_model: Option<String>, // "black_scholes_merton", "cox_ross_rubinstein" or "leisen_reimer"
+    pub early_exercise_premium: Option<f64>, // American value above European; 0 when priced as European
     pub financial_metrics: FinancialMetrics,
     pub reasoning: Vec<String>,
 }
@@ -89,11 +640,53 @@ pub struct MarketSummary {
     pub recommended_position_size: f64, // Percentage of portfolio
 }
 
+// /analyze options, from the query string (GET) or a JSON body (POST). Unset fields take
+// their defaults, and the resolved values are echoed in the response.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnalyzeParams {
+    #[serde(deserialize_with = "deserialize_symbol_list")]
+    pub symbols: Option<Vec<String>>, // Allowlist; news is fetched for these symbols only
+    #[serde(deserialize_with = "deserialize_symbol_list")]
+    pub exclude_symbols: Option<Vec<String>>,
+    pub lookback_hours: Option<f64>, // Ignore news older than this
+    pub max_news: Option<usize>,
+    pub min_confidence: Option<f64>,
+    pub max_risk: Option<f64>,
+    pub max_signals: Option<usize>,
+    pub option_type: Option<String>, // "call", "put" or "both"; unset follows each symbol's sentiment
+    pub exclude_contentless: Option<bool>, // Skip articles without a body
+    pub neutral_sentiment_action: Option<NeutralSentimentAction>, // For symbols inside the neutral band or without news
+    pub vol_surface: Option<bool>, // Fit each symbol's volatility surface (one more chain fetch per symbol)
+}
+
+// Symbol lists arrive as "AAPL,MSFT" in query strings and as arrays in JSON bodies
+fn deserialize_symbol_list<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    let symbols: Vec<String> = match value {
+        None | Some(serde_json::Value::Null) => return Ok(None),
+        Some(serde_json::Value::String(s)) => s.split(',').map(str::to_string).collect(),
+        Some(serde_json::Value::Array(items)) => items.into_iter()
+            .map(|item| match item {
+                serde_json::Value::String(s) => Ok(s),
+                other => Err(serde::de::Error::custom(format!("expected a symbol string, got {other}"))),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(other) => return Err(serde::de::Error::custom(format!("expected a symbol list, got {other}"))),
+    };
+    Ok(Some(symbols))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TradingBotResponse {
+    pub parameters: AnalyzeParams,
     pub market_summary: MarketSummary,
     pub trading_signals: Vec<TradingSignal>,
     pub sentiment_analysis: Vec<SentimentAnalysis>,
+    pub symbol_sentiment: Vec<SymbolSentiment>,
     pub risk_metrics: RiskMetrics,
     pub execution_metadata: ExecutionMetadata,
 }
@@ -141,3 +734,125 @@ pub struct MetricsResult {
     pub kelly_fraction: f64,
     pub composite_score: f64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn parses_occ_symbols() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let cases = [
+            // Padded 21-char OCC form and the unpadded Alpaca form
+            ("SPY   240119C00450000", "SPY", date(2024, 1, 19), OptionRight::Call, 450_000),
+            ("SPY240119C00450000", "SPY", date(2024, 1, 19), OptionRight::Call, 450_000),
+            ("AAPL  240621P00150000", "AAPL", date(2024, 6, 21), OptionRight::Put, 150_000),
+            // Adjusted roots carry a digit
+            ("BRKB1250620P00400000", "BRKB1", date(2025, 6, 20), OptionRight::Put, 400_000),
+            ("F240119C00012500", "F", date(2024, 1, 19), OptionRight::Call, 12_500),
+            ("SPXW  241231P04512500", "SPXW", date(2024, 12, 31), OptionRight::Put, 4_512_500),
+        ];
+        for (input, root, expiration, right, strike_thousandths) in cases {
+            let occ: OccSymbol = input.parse().unwrap_or_else(|e| panic!("{input}: {e}"));
+            assert_eq!(occ, OccSymbol { root: root.to_string(), expiration, right, strike_thousandths }, "{input}");
+        }
+
+        // Fractional strikes come from the thousandths
+        let occ: OccSymbol = "F240119C00012500".parse().unwrap();
+        assert_eq!(occ.strike(), 12.5);
+        let occ: OccSymbol = "SPXW  241231P04512500".parse().unwrap();
+        assert_eq!(occ.strike(), 4512.5);
+    }
+
+    #[test]
+    fn null_fields_deserialize_to_defaults() {
+        let page: NewsPage = serde_json::from_str(r#"{
+            "news": [{
+                "id": 7, "headline": "Fed holds", "summary": null, "content": null, "author": null,
+                "source": null, "url": null, "symbols": null, "created_at": "2024-01-05T14:30:00Z"
+            }],
+            "next_page_token": null
+        }"#).unwrap();
+        let item = &page.news[0];
+        assert_eq!((item.id, item.headline.as_str()), (7, "Fed holds"));
+        assert!(item.summary.is_empty() && item.content.is_empty() && item.author.is_empty() && item.source.is_empty());
+        assert!(item.symbols.is_empty() && item.url.is_none());
+
+        let page: NewsPage = serde_json::from_str(r#"{"news": null}"#).unwrap();
+        assert!(page.news.is_empty());
+
+        let quote: Quote = serde_json::from_str(r#"{"ap": 1.25, "as": 3, "ax": null, "bp": 1.2, "bs": 5, "bx": null}"#).unwrap();
+        assert_eq!((quote.ask_exchange.as_str(), quote.bid_exchange.as_str()), ("", ""));
+        assert_eq!(quote.mid(), Some(1.225));
+        let trade: Trade = serde_json::from_str(r#"{"p": 101.5, "s": 100, "x": null}"#).unwrap();
+        assert_eq!((trade.price, trade.exchange.as_str()), (101.5, ""));
+    }
+
+    #[test]
+    fn contract_volume_comes_from_the_daily_bar_not_the_quote() {
+        let snapshot: OptionSnapshot = serde_json::from_str(r#"{
+            "latestQuote": {"ap": 2.1, "as": 4200, "bp": 2.0, "bs": 900},
+            "dailyBar": {"o": 1.9, "h": 2.2, "l": 1.8, "c": 2.05, "v": 37, "n": 12, "vw": 2.01}
+        }"#).unwrap();
+        let contract = OptionContract { snapshot, ..Default::default() };
+        assert_eq!(contract.volume(), 37);
+
+        // No trades yet today
+        let contract = OptionContract { snapshot: OptionSnapshot { daily_bar: None, ..contract.snapshot }, ..Default::default() };
+        assert_eq!(contract.volume(), 0);
+    }
+
+    #[test]
+    fn parses_chain_sort_fields() {
+        let cases = [
+            ("expiration", ChainSortField::Expiration),
+            ("expiration_date", ChainSortField::Expiration),
+            ("Strike", ChainSortField::Strike),
+            ("strike_price", ChainSortField::Strike),
+            ("volume", ChainSortField::Volume),
+            ("OPEN_INTEREST", ChainSortField::OpenInterest),
+            ("iv", ChainSortField::ImpliedVolatility),
+            ("implied_volatility", ChainSortField::ImpliedVolatility),
+            ("spread_pct", ChainSortField::SpreadPct),
+            ("delta", ChainSortField::Delta),
+            ("mid", ChainSortField::Mid),
+        ];
+        for (input, field) in cases {
+            assert_eq!(input.parse::<ChainSortField>(), Ok(field), "{input}");
+        }
+        for invalid in ["", "gamma", "open interest", "expiry"] {
+            let error = invalid.parse::<ChainSortField>().unwrap_err();
+            assert!(error.contains(&format!("'{invalid}'")), "{error}");
+        }
+    }
+
+    #[test]
+    fn displays_without_root_padding() {
+        let occ: OccSymbol = "SPY   240119C00450000".parse().unwrap();
+        assert_eq!(occ.to_string(), "SPY240119C00450000");
+        assert_eq!(occ.to_string().parse::<OccSymbol>().unwrap(), occ);
+    }
+
+    #[test]
+    fn rejects_malformed_occ_symbols() {
+        type Expected = fn(String) -> OccSymbolError;
+        let cases: [(&str, Expected); 12] = [
+            ("", OccSymbolError::TooShort),
+            ("SPY240119C0045", OccSymbolError::TooShort),
+            ("240119C00450000", OccSymbolError::TooShort),
+            ("SPŸ240119C00450000", OccSymbolError::TooShort),
+            ("      240119C00450000", OccSymbolError::InvalidRoot),
+            ("BRK.B250620P00400000", OccSymbolError::InvalidRoot),
+            ("TOOLONG240119C00450000", OccSymbolError::InvalidRoot),
+            ("SPY241319C00450000", OccSymbolError::InvalidExpiration),
+            ("SPY240230C00450000", OccSymbolError::InvalidExpiration),
+            ("SPY24O119C00450000", OccSymbolError::InvalidExpiration),
+            ("SPY240119X00450000", OccSymbolError::InvalidRight),
+            ("SPY240119C0045000A", OccSymbolError::InvalidStrike),
+        ];
+        for (input, error) in cases {
+            assert_eq!(input.parse::<OccSymbol>(), Err(error(input.to_string())), "{input}");
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use crate::pricing::{implied_volatility, BsmInputs};
+use crate::types::{OccSymbol, OptionChain, OptionRight, OptionSnapshot, Quote};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// Inverse standard normal CDF at 0.75: |d1| of a 25-delta option
+const D1_25_DELTA: f64 = 0.674_489_750_196_082;
+// Smiles are summarised at the expiry closest to this many calendar days
+const REFERENCE_DAYS: i64 = 30;
+const MIN_SMILE_IV: f64 = 0.01;
+
+// Implied volatility smile for one expiry: a least-squares parabola in log-moneyness
+// k = ln(strike / forward), held flat outside the strikes it was fitted on
+#[derive(Debug, Clone)]
+struct Smile {
+    expiration: NaiveDate,
+    days: i64,
+    years: f64,
+    coefficients: [f64; 3],
+    k_range: (f64, f64),
+    points: usize,
+}
+
+impl Smile {
+    fn iv(&self, log_moneyness: f64) -> f64 {
+        let k = log_moneyness.clamp(self.k_range.0, self.k_range.1);
+        let [a, b, c] = self.coefficients;
+        (a + b * k + c * k * k).max(MIN_SMILE_IV)
+    }
+
+    // Log-moneyness of the 25-delta call (sign 1) or put (sign -1) on this smile, by fixed-point
+    // iteration since the strike depends on its own volatility
+    fn k_25_delta(&self, sign: f64) -> f64 {
+        let sqrt_t = self.years.sqrt();
+        let mut k = 0.0;
+        for _ in 0..20 {
+            let sigma = self.iv(k);
+            k = 0.5 * sigma * sigma * self.years + sign * D1_25_DELTA * sigma * sqrt_t;
+        }
+        k
+    }
+
+    fn summary(&self) -> SmileSummary {
+        let atm_iv = self.iv(0.0);
+        let call_25 = self.iv(self.k_25_delta(1.0));
+        let put_25 = self.iv(self.k_25_delta(-1.0));
+        SmileSummary {
+            expiration: self.expiration.format("%Y-%m-%d").to_string(),
+            days_to_expiry: self.days,
+            atm_iv,
+            risk_reversal_25d: call_25 - put_25,
+            butterfly_25d: 0.5 * (call_25 + put_25) - atm_iv,
+            points: self.points,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SmileSummary {
+    pub expiration: String,
+    pub days_to_expiry: i64,
+    pub atm_iv: f64,
+    pub risk_reversal_25d: f64, // 25-delta call IV minus 25-delta put IV
+    pub butterfly_25d: f64,     // Average 25-delta wing IV minus ATM IV
+    pub points: usize,
+}
+
+// Headline skew figures at the expiry nearest 30 days, plus every fitted expiry
+#[derive(Debug, Clone, Serialize)]
+pub struct VolSurfaceSummary {
+    pub reference_expiration: String,
+    pub atm_iv: f64,
+    pub risk_reversal_25d: f64,
+    pub butterfly_25d: f64,
+    pub term_structure: Vec<SmileSummary>,
+    pub truncated: bool, // Fitted on a chain cut short by the pagination caps
+}
+
+// Implied volatility surface for one underlying, built from an option chain. Each smile is a
+// plain quadratic in log-moneyness, not SVI or a spline: it reads skew and curvature near the
+// money well, but nothing stops the wings from admitting calendar or butterfly arbitrage, so
+// it is for ranking contracts against each other rather than for pricing off
+#[derive(Debug, Clone)]
+pub struct VolSurface {
+    spot: f64,
+    rate: f64,
+    dividend_yield: f64,
+    as_of: NaiveDate,
+    smiles: Vec<Smile>, // Ascending expiry
+    truncated: bool,
+}
+
+impl VolSurface {
+    // Fit one smile per expiry from the out-of-the-money side of each strike, where quotes are
+    // most liquid and early exercise is worth least; expiries with no usable IV are skipped
+    pub fn build(chain: &OptionChain, spot: f64, rate: f64, dividend_yield: f64, as_of: NaiveDate) -> Self {
+        let mut surface = VolSurface { spot, rate, dividend_yield, as_of, smiles: Vec::new(), truncated: chain.truncated };
+
+        let mut by_expiry = BTreeMap::<NaiveDate, Vec<(f64, f64)>>::new();
+        for (contract_key, snapshot) in &chain.snapshots {
+            let Ok(occ) = contract_key.parse::<OccSymbol>() else { continue };
+            if occ.expiration <= as_of {
+                continue;
+            }
+            let k = (occ.strike() / surface.forward(occ.expiration)).ln();
+            let out_of_the_money = match occ.right {
+                OptionRight::Call => k >= 0.0,
+                OptionRight::Put => k < 0.0,
+            };
+            if out_of_the_money && let Some(iv) = surface.contract_iv(snapshot, &occ) {
+                by_expiry.entry(occ.expiration).or_default().push((k, iv));
+            }
+        }
+
+        surface.smiles = by_expiry
+            .into_iter()
+            .map(|(expiration, points)| {
+                let days = (expiration - as_of).num_days();
+                Smile {
+                    expiration,
+                    days,
+                    years: days as f64 / 365.0,
+                    coefficients: fit_parabola(&points),
+                    k_range: points.iter().fold((f64::MAX, f64::MIN), |(lo, hi), (k, _)| (lo.min(*k), hi.max(*k))),
+                    points: points.len(),
+                }
+            })
+            .collect();
+        surface
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.smiles.is_empty()
+    }
+
+    // Market IV of one contract: the vendor's when present, otherwise solved from the quote mid
+    pub fn contract_iv(&self, snapshot: &OptionSnapshot, occ: &OccSymbol) -> Option<f64> {
+        if let Some(iv) = snapshot.implied_volatility.filter(|iv| *iv > 0.0) {
+            return Some(iv);
+        }
+        let mid = snapshot.latest_quote.as_ref().and_then(Quote::mid)?;
+        let inputs = BsmInputs {
+            spot: self.spot,
+            strike: occ.strike(),
+            years: self.years_to(occ.expiration),
+            rate: self.rate,
+            dividend_yield: self.dividend_yield,
+            volatility: 0.0,
+            right: occ.right,
+        };
+        implied_volatility(mid, &inputs).ok()
+    }
+
+    // Fitted IV at a strike and expiry; between fitted expiries total variance is interpolated
+    // linearly in time at constant log-moneyness, beyond them the nearest smile is used
+    pub fn iv_at(&self, strike: f64, expiration: NaiveDate) -> Option<f64> {
+        let years = self.years_to(expiration);
+        let k = (strike / self.forward(expiration)).ln();
+        let after = self.smiles.iter().position(|smile| smile.expiration >= expiration);
+        let iv = match after {
+            Some(0) => self.smiles[0].iv(k),
+            Some(i) if self.smiles[i].expiration == expiration => self.smiles[i].iv(k),
+            Some(i) => {
+                let (near, far) = (&self.smiles[i - 1], &self.smiles[i]);
+                let near_variance = near.iv(k).powi(2) * near.years;
+                let far_variance = far.iv(k).powi(2) * far.years;
+                let weight = (years - near.years) / (far.years - near.years);
+                ((near_variance + weight * (far_variance - near_variance)) / years).sqrt()
+            }
+            None => self.smiles.last()?.iv(k),
+        };
+        Some(iv)
+    }
+
+    pub fn summary(&self) -> Option<VolSurfaceSummary> {
+        let reference = self.smiles.iter().min_by_key(|smile| (smile.days - REFERENCE_DAYS).abs())?.summary();
+        Some(VolSurfaceSummary {
+            reference_expiration: reference.expiration.clone(),
+            atm_iv: reference.atm_iv,
+            risk_reversal_25d: reference.risk_reversal_25d,
+            butterfly_25d: reference.butterfly_25d,
+            term_structure: self.smiles.iter().map(Smile::summary).collect(),
+            truncated: self.truncated,
+        })
+    }
+
+    fn years_to(&self, expiration: NaiveDate) -> f64 {
+        (expiration - self.as_of).num_days().max(1) as f64 / 365.0
+    }
+
+    fn forward(&self, expiration: NaiveDate) -> f64 {
+        self.spot * ((self.rate - self.dividend_yield) * self.years_to(expiration)).exp()
+    }
+}
+
+// Least-squares a + b*k + c*k^2 through (k, iv) points; flat at the mean when there are
+// too few distinct strikes to pin down a curve
+fn fit_parabola(points: &[(f64, f64)]) -> [f64; 3] {
+    let n = points.len() as f64;
+    let mean = points.iter().map(|(_, iv)| iv).sum::<f64>() / n;
+    if points.len() < 3 {
+        return [mean, 0.0, 0.0];
+    }
+
+    // Normal equations: sum k^(i+j) * coef_j = sum iv * k^i
+    let mut matrix = [[0.0; 4]; 3];
+    for (k, iv) in points {
+        let powers = [1.0, *k, k * k];
+        for i in 0..3 {
+            for j in 0..3 {
+                matrix[i][j] += powers[i] * powers[j];
+            }
+            matrix[i][3] += iv * powers[i];
+        }
+    }
+
+    // Gaussian elimination with partial pivoting
+    for col in 0..3 {
+        let pivot = (col..3)
+            .max_by(|a, b| matrix[*a][col].abs().total_cmp(&matrix[*b][col].abs()))
+            .unwrap_or(col);
+        matrix.swap(col, pivot);
+        if matrix[col][col].abs() < 1e-12 {
+            return [mean, 0.0, 0.0];
+        }
+        let pivot_row = matrix[col];
+        for row in matrix.iter_mut().skip(col + 1) {
+            let factor = row[col] / pivot_row[col];
+            for (value, pivot_value) in row.iter_mut().zip(pivot_row).skip(col) {
+                *value -= factor * pivot_value;
+            }
+        }
+    }
+    let mut coefficients = [0.0; 3];
+    for row in (0..3).rev() {
+        let known: f64 = (row + 1..3).map(|j| matrix[row][j] * coefficients[j]).sum();
+        coefficients[row] = (matrix[row][3] - known) / matrix[row][row];
+    }
+    coefficients
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pricing::black_scholes_merton;
+
+    // Chain priced off a known smile: 20% ATM, skewed towards puts and curving up in the wings
+    fn synthetic_chain(as_of: NaiveDate, spot: f64, rate: f64) -> OptionChain {
+        let smile = |k: f64| 0.2 - 0.1 * k + 0.3 * k * k;
+        let mut chain = OptionChain::default();
+        for days in [30, 90] {
+            let expiration = as_of + chrono::Duration::days(days);
+            let years = days as f64 / 365.0;
+            let forward = spot * (rate * years).exp();
+            for strike in (70..=130).step_by(5).map(f64::from) {
+                for right in [OptionRight::Call, OptionRight::Put] {
+                    let price = black_scholes_merton(&BsmInputs {
+                        spot,
+                        strike,
+                        years,
+                        rate,
+                        dividend_yield: 0.0,
+                        volatility: smile((strike / forward).ln()),
+                        right,
+                    })
+                    .unwrap()
+                    .price;
+                    let flag = if right == OptionRight::Call { 'C' } else { 'P' };
+                    let key = format!("XYZ{}{flag}{:08}", expiration.format("%y%m%d"), (strike * 1000.0) as u64);
+                    let quote = Quote { bid_price: price, ask_price: price, ..Default::default() };
+                    chain.snapshots.insert(key, OptionSnapshot { latest_quote: Some(quote), ..Default::default() });
+                }
+            }
+        }
+        chain
+    }
+
+    #[test]
+    fn recovers_the_smile_it_was_priced_with() {
+        let as_of = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let surface = VolSurface::build(&synthetic_chain(as_of, 100.0, 0.04), 100.0, 0.04, 0.0, as_of);
+        let summary = surface.summary().unwrap();
+
+        assert_eq!(summary.term_structure.len(), 2);
+        assert_eq!(summary.reference_expiration, "2024-02-01");
+        assert!((summary.atm_iv - 0.2).abs() < 1e-4, "atm {}", summary.atm_iv);
+        // Put skew: the 25-delta put trades above the 25-delta call
+        assert!(summary.risk_reversal_25d < 0.0);
+        assert!(summary.butterfly_25d > 0.0);
+        assert!(!summary.truncated);
+    }
+
+    #[test]
+    fn interpolates_total_variance_between_expiries() {
+        let as_of = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let surface = VolSurface::build(&synthetic_chain(as_of, 100.0, 0.0), 100.0, 0.0, 0.0, as_of);
+        let between = surface.iv_at(100.0, as_of + chrono::Duration::days(60)).unwrap();
+        assert!((between - 0.2).abs() < 1e-4, "between {between}");
+        // Beyond the last expiry the far smile is used as is
+        let beyond = surface.iv_at(80.0, as_of + chrono::Duration::days(400)).unwrap();
+        let k = (80.0_f64 / 100.0).ln();
+        assert!((beyond - (0.2 - 0.1 * k + 0.3 * k * k)).abs() < 1e-3, "beyond {beyond}");
+    }
+}